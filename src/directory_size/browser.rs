@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::directory_size::directory_size::{convert, directory_sizes};
+
+const BAR_WIDTH: usize = 10;
+
+/// An in-memory terminal screen, one `String` per row, that the browser renders into.
+pub struct Screen {
+    width: usize,
+    height: usize,
+    lines: Vec<String>,
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Screen {
+        Screen { width, height, lines: vec![String::new(); height] }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.iter_mut().for_each(|line| line.clear());
+    }
+
+    /// write text on row, cut to the screen width
+    pub fn put(&mut self, row: usize, text: &str) {
+        if let Some(line) = self.lines.get_mut(row) {
+            *line = text.chars().take(self.width).collect();
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Back,
+    ToggleSort,
+    Delete,
+    Yes,
+    No,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    BySize,
+    ByName,
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    is_dir: bool,
+}
+
+/// Browse the size tree of a scan, ncdu-style.
+///
+/// Directory totals come from `directory_sizes`; the files of a directory are listed when it is entered.
+pub struct Browser {
+    root: PathBuf,
+    sizes: BTreeMap<PathBuf, u64>,
+    current: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    sort: SortOrder,
+    pending_delete: Option<PathBuf>,
+    message: String,
+}
+
+impl Browser {
+    pub fn scan(root: PathBuf) -> io::Result<Browser> {
+        let sizes = directory_sizes(root.clone())?;
+        let mut browser = Browser {
+            current: root.clone(),
+            root,
+            sizes,
+            entries: Vec::new(),
+            selected: 0,
+            sort: SortOrder::BySize,
+            pending_delete: None,
+            message: String::new(),
+        };
+        browser.list()?;
+        Ok(browser)
+    }
+
+    pub fn current(&self) -> &Path {
+        &self.current
+    }
+
+    pub fn total(&self) -> u64 {
+        self.sizes.get(&self.root).copied().unwrap_or(0)
+    }
+
+    /// apply one key press, false once the browser should quit
+    pub fn handle(&mut self, key: Key) -> io::Result<bool> {
+        if let Some(path) = self.pending_delete.take() {
+            if key == Key::Yes {
+                self.delete(&path)?;
+            } else {
+                self.message = "delete cancelled".to_string();
+            }
+            return Ok(true);
+        }
+
+        self.message.clear();
+        match key {
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1)),
+            Key::Enter => {
+                if let Some(entry) = self.entries.get(self.selected).filter(|entry| entry.is_dir) {
+                    self.current = entry.path.clone();
+                    self.selected = 0;
+                    self.list()?;
+                }
+            }
+            Key::Back => {
+                if self.current != self.root {
+                    let child = self.current.clone();
+                    self.current.pop();
+                    self.list()?;
+                    self.selected = self.entries.iter().position(|entry| entry.path == child).unwrap_or(0);
+                }
+            }
+            Key::ToggleSort => {
+                self.sort = match self.sort {
+                    SortOrder::BySize => SortOrder::ByName,
+                    SortOrder::ByName => SortOrder::BySize,
+                };
+                self.sort_entries();
+            }
+            Key::Delete => {
+                if let Some(entry) = self.entries.get(self.selected) {
+                    self.pending_delete = Some(entry.path.clone());
+                }
+            }
+            Key::Yes | Key::No => {}
+            Key::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn render(&self, screen: &mut Screen) {
+        screen.clear();
+        let sort = match self.sort {
+            SortOrder::BySize => "size",
+            SortOrder::ByName => "name",
+        };
+        let current_size = self.sizes.get(&self.current).copied().unwrap_or(0);
+        screen.put(0, &format!("--- {} {} (total {}) sort: {}",
+                               self.current.display(), convert(current_size), convert(self.total()), sort));
+
+        let rows = screen.height.saturating_sub(2);
+        let first = (self.selected + 1).saturating_sub(rows);
+        let largest = self.entries.iter().map(|entry| entry.size).max().unwrap_or(0).max(1);
+        for (row, (index, entry)) in self.entries.iter().enumerate().skip(first).take(rows).enumerate() {
+            let filled = (entry.size * BAR_WIDTH as u64 / largest) as usize;
+            let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+            screen.put(row + 1, &format!("{}{:>10} [{:<width$}] {}{}",
+                                         if index == self.selected { ">" } else { " " },
+                                         convert(entry.size),
+                                         "#".repeat(filled),
+                                         name,
+                                         if entry.is_dir { "/" } else { "" },
+                                         width = BAR_WIDTH));
+        }
+
+        let status = match &self.pending_delete {
+            Some(path) => format!("delete {}? (y/n)", path.display()),
+            None if self.entries.is_empty() => "empty directory".to_string(),
+            None => self.message.clone(),
+        };
+        screen.put(screen.height.saturating_sub(1), &status);
+    }
+
+    fn list(&mut self) -> io::Result<()> {
+        self.entries.clear();
+        for dir_entry in fs::read_dir(&self.current)? {
+            let path = dir_entry?.path();
+            let metadata = fs::symlink_metadata(&path)?;
+            let size = if metadata.is_dir() {
+                self.sizes.get(&path).copied().unwrap_or(0)
+            } else {
+                metadata.len()
+            };
+            self.entries.push(Entry { path, size, is_dir: metadata.is_dir() });
+        }
+        self.sort_entries();
+        Ok(())
+    }
+
+    fn sort_entries(&mut self) {
+        match self.sort {
+            SortOrder::BySize => self.entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))),
+            SortOrder::ByName => self.entries.sort_by(|a, b| a.path.cmp(&b.path)),
+        }
+    }
+
+    fn delete(&mut self, path: &Path) -> io::Result<()> {
+        let entry = match self.entries.iter().find(|entry| entry.path == path) {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+        let size = entry.size;
+        if entry.is_dir {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+
+        self.sizes.retain(|dir, _| !dir.starts_with(path));
+        for ancestor in path.ancestors().skip(1) {
+            if !ancestor.starts_with(&self.root) {
+                break;
+            }
+            if let Some(total) = self.sizes.get_mut(ancestor) {
+                *total = total.saturating_sub(size);
+            }
+        }
+        self.message = format!("deleted {} ({})", path.display(), convert(size));
+        self.list()?;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+}
+
+/// browse root in the terminal until `q` is pressed.
+/// keys: arrows or hjkl to move, enter to open a directory, `s` to toggle the sort order, `d` to delete.
+pub fn browse(root: PathBuf) -> io::Result<()> {
+    let mut browser = Browser::scan(root)?;
+    let (height, width) = terminal_size().unwrap_or((24, 80));
+    let mut screen = Screen::new(width, height);
+
+    let _raw_mode = RawMode::enter()?;
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        browser.render(&mut screen);
+        write!(stdout, "\x1b[2J\x1b[H{}", screen.lines().join("\r\n"))?;
+        stdout.flush()?;
+        let key = match read_key(&mut stdin)? {
+            Some(key) => key,
+            None => continue,
+        };
+        if !browser.handle(key)? {
+            return Ok(());
+        }
+    }
+}
+
+fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    let mut byte = [0u8; 1];
+    input.read_exact(&mut byte)?;
+    let key = match byte[0] {
+        b'k' => Key::Up,
+        b'j' => Key::Down,
+        b'l' | b'\n' | b'\r' => Key::Enter,
+        b'h' | 0x7f => Key::Back,
+        b's' => Key::ToggleSort,
+        b'd' => Key::Delete,
+        b'y' => Key::Yes,
+        b'n' => Key::No,
+        b'q' => Key::Quit,
+        0x1b => {
+            let mut sequence = [0u8; 2];
+            input.read_exact(&mut sequence)?;
+            match sequence {
+                [b'[', b'A'] => Key::Up,
+                [b'[', b'B'] => Key::Down,
+                [b'[', b'C'] => Key::Enter,
+                [b'[', b'D'] => Key::Back,
+                _ => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(key))
+}
+
+/// the terminal switched to unbuffered input without echo, put back as it was when dropped, even on an error or panic
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        let output = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output()?;
+        let saved = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        stty(&["-icanon", "-echo"])?;
+        Ok(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let restored = if self.saved.is_empty() { "sane" } else { self.saved.as_str() };
+        let _ = stty(&[restored]);
+        println!();
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<()> {
+    Command::new("stty").args(args).stdin(Stdio::inherit()).status()?;
+    Ok(())
+}
+
+/// rows and columns of the terminal
+fn terminal_size() -> Option<(usize, usize)> {
+    let output = Command::new("stty").arg("size").stdin(Stdio::inherit()).output().ok()?;
+    let size = String::from_utf8(output.stdout).ok()?;
+    let mut parts = size.split_whitespace().map(|part| part.parse().ok());
+    Some((parts.next()??, parts.next()??))
+}
+
+#[cfg(test)]
+fn rendered(browser: &Browser) -> Vec<String> {
+    let mut screen = Screen::new(120, 8);
+    browser.render(&mut screen);
+    screen.lines().iter().map(|line| line.trim_end().to_string()).collect()
+}
+
+#[test]
+fn browser_navigation_test() {
+    let root = crate::directory_size::directory_size::test_dir("browser");
+    fs::create_dir_all(root.join("big")).unwrap();
+    fs::write(root.join("big/data.bin"), vec![0u8; 8192]).unwrap();
+    fs::write(root.join("a.txt"), [0u8; 100]).unwrap();
+    fs::write(root.join("z.txt"), [0u8; 2000]).unwrap();
+
+    let mut browser = Browser::scan(root.clone()).unwrap();
+    let lines = rendered(&browser);
+    assert!(lines[0].starts_with(&format!("--- {}", root.display())));
+    assert!(lines[1].starts_with(">") && lines[1].ends_with("[##########] big/"));
+    assert!(lines[2].ends_with("z.txt"));
+    assert!(lines[3].ends_with("a.txt"));
+
+    browser.handle(Key::ToggleSort).unwrap();
+    let lines = rendered(&browser);
+    assert!(lines[0].ends_with("sort: name"));
+    assert!(lines[1].ends_with("a.txt") && lines[1].starts_with(">"));
+
+    browser.handle(Key::Down).unwrap();
+    browser.handle(Key::Enter).unwrap();
+    assert_eq!(browser.current(), root.join("big"));
+    let lines = rendered(&browser);
+    assert!(lines[1].contains("8.00KB") && lines[1].ends_with("data.bin"));
+
+    browser.handle(Key::Back).unwrap();
+    assert_eq!(browser.current(), root);
+    assert!(rendered(&browser)[2].starts_with(">"));
+    assert!(browser.handle(Key::Quit).map(|running| !running).unwrap());
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn browser_delete_test() {
+    let root = crate::directory_size::directory_size::test_dir("browser_delete");
+    fs::create_dir_all(root.join("cache")).unwrap();
+    fs::write(root.join("cache/blob"), vec![0u8; 5000]).unwrap();
+    fs::write(root.join("keep.txt"), [0u8; 10]).unwrap();
+
+    let mut browser = Browser::scan(root.clone()).unwrap();
+    let total = browser.total();
+    let cache = fs::symlink_metadata(root.join("cache")).unwrap().len() + 5000;
+
+    browser.handle(Key::Delete).unwrap();
+    assert!(rendered(&browser)[7].starts_with("delete") && rendered(&browser)[7].ends_with("(y/n)"));
+    browser.handle(Key::No).unwrap();
+    assert!(root.join("cache").exists());
+    assert_eq!(rendered(&browser)[7], "delete cancelled");
+
+    browser.handle(Key::Delete).unwrap();
+    browser.handle(Key::Yes).unwrap();
+    assert!(!root.join("cache").exists());
+    assert_eq!(browser.total(), total - cache);
+    let lines = rendered(&browser);
+    assert!(lines[1].starts_with(">") && lines[1].ends_with("keep.txt"));
+    assert!(lines[2].is_empty());
+    assert!(lines[7].starts_with("deleted"));
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn read_key_test() {
+    let mut input: &[u8] = b"j\x1b[Aqx";
+    assert_eq!(read_key(&mut input).unwrap(), Some(Key::Down));
+    assert_eq!(read_key(&mut input).unwrap(), Some(Key::Up));
+    assert_eq!(read_key(&mut input).unwrap(), Some(Key::Quit));
+    assert_eq!(read_key(&mut input).unwrap(), None);
+}
\ No newline at end of file
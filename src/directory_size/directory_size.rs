@@ -1,38 +1,129 @@
 use std::{fs, io};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 
+use crate::directory_size::filter::Filter;
 
-pub fn directory_size(path: PathBuf) -> io::Result<u64> {
-    let mut total_size = 0;
-    let mut dirs = vec![path];
-    while let Some(dir) = dirs.pop() {
+
+/// which symbolic links the walk follows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymlinkPolicy {
+    /// count the links themselves, never their targets
+    Never,
+    /// follow the path given to the walk when it is a link, but no link found inside the tree
+    CommandLine,
+    /// follow every link
+    Always,
+}
+
+/// walk the tree under path depth first, calling visit with every entry (the root included)
+pub fn walk<F>(path: PathBuf, visit: F) -> io::Result<()>
+    where F: FnMut(&Path, &Metadata) {
+    walk_with(path, SymlinkPolicy::Never, visit)
+}
+
+/// walk like `walk`, following symbolic links as the policy says.
+/// a followed link is visited with the metadata of its target, unless the target is a directory the walk has
+/// already entered (identified by device and inode), then the link itself is visited so that cycles end.
+pub fn walk_with<F>(path: PathBuf, policy: SymlinkPolicy, mut visit: F) -> io::Result<()>
+    where F: FnMut(&Path, &Metadata) {
+    let mut visited_dirs = HashSet::new();
+    let mut dirs = vec![(path, true)];
+    while let Some((dir, is_root)) = dirs.pop() {
         //println!("{}", dir.display());
-        let metadata = fs::symlink_metadata(&dir)?;
-        if metadata.is_dir() {
+        let mut metadata = fs::symlink_metadata(&dir)?;
+        let follow = match policy {
+            SymlinkPolicy::Never => false,
+            SymlinkPolicy::CommandLine => is_root,
+            SymlinkPolicy::Always => true,
+        };
+        if follow && metadata.file_type().is_symlink() {
+            // a dangling link is counted as the link itself
+            if let Ok(target) = fs::metadata(&dir) {
+                if !target.is_dir() || !visited_dirs.contains(&(target.dev(), target.ino())) {
+                    metadata = target;
+                }
+            }
+        }
+        if metadata.is_dir() && visited_dirs.insert((metadata.dev(), metadata.ino())) {
             let read_dir = fs::read_dir(&dir)?;
             for dir_entry in read_dir {
                 let entry = dir_entry?;
-                dirs.push(entry.path());
+                dirs.push((entry.path(), false));
             }
         }
-        total_size += metadata.len();
+        visit(&dir, &metadata);
     }
+    Ok(())
+}
+
+pub fn directory_size(path: PathBuf) -> io::Result<u64> {
+    directory_size_with(path, SymlinkPolicy::Never)
+}
+
+pub fn directory_size_with(path: PathBuf, policy: SymlinkPolicy) -> io::Result<u64> {
+    let mut total_size = 0;
+    walk_with(path, policy, |_, metadata| total_size += metadata.len())?;
+    Ok(total_size)
+}
 
+/// the total size of the regular files under path kept by filter
+pub fn directory_size_filtered(path: PathBuf, filter: &Filter) -> io::Result<u64> {
+    let mut total_size = 0;
+    let root = path.clone();
+    walk(path, |entry, metadata| {
+        let relative = entry.strip_prefix(&root).unwrap_or(entry);
+        if metadata.is_file() && filter.matches(relative, metadata) {
+            total_size += metadata.len();
+        }
+    })?;
     Ok(total_size)
 }
 
+/// the total size of every directory under path, each one counting its whole subtree
+pub fn directory_sizes(path: PathBuf) -> io::Result<BTreeMap<PathBuf, u64>> {
+    let mut sizes = BTreeMap::new();
+    let root = path.clone();
+    walk(path, |entry, metadata| {
+        if metadata.is_dir() {
+            sizes.entry(entry.to_path_buf()).or_insert(0);
+        }
+        for ancestor in entry.ancestors() {
+            if !ancestor.starts_with(&root) {
+                break;
+            }
+            if ancestor == entry && !metadata.is_dir() {
+                continue;
+            }
+            *sizes.entry(ancestor.to_path_buf()).or_insert(0) += metadata.len();
+        }
+    })?;
+    Ok(sizes)
+}
+
 const UNIT_SIZE: u64 = 1024;
 
-fn convert(size: u64) -> String {
-    let size_units = vec!["B", "KB", "MB", "GB", "TB"];
-    for index in (1..size_units.len()-1).rev(){
+/// human readable size, e.g. `1.50MB`
+pub fn convert(size: u64) -> String {
+    let size_units = ["B", "KB", "MB", "GB", "TB"];
+    for index in (1..size_units.len()).rev() {
         let step: u64 = UNIT_SIZE.pow(index as u32);
-        if size as u64 > step {
+        if size >= step {
             return format!("{:.2}{}", (size as f64 / step as f64), size_units[index]);
         }
     }
-    println!("{:?}", size_units);
-    size.to_string()
+    format!("{}{}", size, size_units[0])
+}
+
+/// create an empty scratch directory for a test
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("directory_size_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create test dir error");
+    dir
 }
 
 #[test]
@@ -41,4 +132,77 @@ fn directory_size_test() {
         Ok(size) => println!("total size: {}", size),
         Err(err) => println!("{:?}", err),
     };
+}
+
+#[test]
+fn directory_sizes_test() {
+    let root = test_dir("sizes");
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::write(root.join("top.txt"), [0u8; 10]).unwrap();
+    fs::write(root.join("a/one.txt"), [0u8; 100]).unwrap();
+    fs::write(root.join("a/b/two.txt"), [0u8; 1000]).unwrap();
+
+    let sizes = directory_sizes(root.clone()).unwrap();
+    let dir_len = |path: &Path| fs::symlink_metadata(path).unwrap().len();
+    let b = dir_len(&root.join("a/b")) + 1000;
+    let a = dir_len(&root.join("a")) + 100 + b;
+    assert_eq!(sizes[&root.join("a/b")], b);
+    assert_eq!(sizes[&root.join("a")], a);
+    assert_eq!(sizes[&root], dir_len(&root) + 10 + a);
+    assert_eq!(sizes.len(), 3);
+    assert_eq!(sizes[&root], directory_size(root.clone()).unwrap());
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn convert_test() {
+    assert_eq!(convert(512), "512B");
+    assert_eq!(convert(1024), "1.00KB");
+    assert_eq!(convert(3 * 1024 * 1024 / 2), "1.50MB");
+    assert_eq!(convert(2 * UNIT_SIZE.pow(4)), "2.00TB");
+}
+
+#[test]
+fn symlink_policy_test() {
+    use std::os::unix::fs::symlink;
+
+    let root = test_dir("symlink");
+    let workspace = root.join("workspace");
+    let shared = root.join("shared");
+    fs::create_dir_all(&workspace).unwrap();
+    fs::create_dir_all(&shared).unwrap();
+    fs::write(shared.join("data.bin"), [0u8; 5000]).unwrap();
+    fs::write(workspace.join("own.txt"), [0u8; 10]).unwrap();
+    symlink(&shared, workspace.join("shared")).unwrap();
+    symlink(&workspace, root.join("workspace_link")).unwrap();
+
+    let never = directory_size_with(workspace.clone(), SymlinkPolicy::Never).unwrap();
+    let always = directory_size_with(workspace.clone(), SymlinkPolicy::Always).unwrap();
+    let link_len = fs::symlink_metadata(workspace.join("shared")).unwrap().len();
+    assert_eq!(always - never, directory_size(shared.clone()).unwrap() - link_len);
+
+    let link = root.join("workspace_link");
+    assert_eq!(directory_size_with(link.clone(), SymlinkPolicy::Never).unwrap(),
+               fs::symlink_metadata(&link).unwrap().len());
+    assert_eq!(directory_size_with(link, SymlinkPolicy::CommandLine).unwrap(), never);
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn symlink_cycle_test() {
+    use std::os::unix::fs::symlink;
+
+    let root = test_dir("symlink_cycle");
+    fs::create_dir_all(root.join("a")).unwrap();
+    fs::write(root.join("a/file.txt"), [0u8; 100]).unwrap();
+    symlink(".", root.join("a/self")).unwrap();
+    symlink(root.join("a"), root.join("a/back")).unwrap();
+    symlink("missing", root.join("a/dangling")).unwrap();
+
+    let mut visited = Vec::new();
+    walk_with(root.clone(), SymlinkPolicy::Always, |path, _| visited.push(path.to_path_buf())).unwrap();
+    assert_eq!(visited.len(), 6);
+    assert_eq!(directory_size_with(root.clone(), SymlinkPolicy::Always).unwrap(),
+               directory_size_with(root.clone(), SymlinkPolicy::Never).unwrap());
+    fs::remove_dir_all(root).unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,273 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::Hasher;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::directory_size::directory_size::walk;
+use crate::example::parallel::map_parallel;
+
+/// size of the head and tail blocks compared before hashing whole files
+const BLOCK_SIZE: usize = 4096;
+/// every hashing thread streams files through one buffer of this size
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// files with identical content; hard links to one inode only appear once
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// bytes freed by keeping one copy and removing the others
+    pub fn reclaimable(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// the duplicate groups of a scan, with the files that could not be read and were left out
+#[derive(Debug)]
+pub struct Duplicates {
+    pub groups: Vec<DuplicateGroup>,
+    pub skipped: Vec<(PathBuf, io::Error)>,
+}
+
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+}
+
+/// find groups of identical files under path, biggest reclaimable space first.
+/// files are grouped by size, then by a hash of their first and last blocks, then by a hash of their whole content,
+/// and every group left is confirmed by comparing the bytes, since a hash match alone could be a collision.
+/// a file that cannot be read, or vanished during the scan, is skipped and reported instead of ending the scan.
+pub fn find_duplicates(path: PathBuf) -> io::Result<Duplicates> {
+    let mut inodes = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    walk(path, |entry, metadata| {
+        if metadata.is_file() && metadata.len() > 0 && inodes.insert((metadata.dev(), metadata.ino())) {
+            by_size.entry(metadata.len()).or_default().push(Candidate {
+                path: entry.to_path_buf(),
+                size: metadata.len(),
+            });
+        }
+    })?;
+
+    let mut skipped = Vec::new();
+    let groups: Vec<Vec<Candidate>> = by_size.into_values().filter(|group| group.len() > 1).collect();
+    let groups = refine(groups, partial_hash, &mut skipped);
+    let (small, large): (Vec<_>, Vec<_>) = groups.into_iter()
+        .partition(|group| group[0].size <= 2 * BLOCK_SIZE as u64);
+    let mut groups = refine(large, full_hash, &mut skipped);
+    groups.extend(small);
+    let groups = groups.into_iter().flat_map(|group| confirm(group, &mut skipped)).collect::<Vec<_>>();
+
+    let mut duplicates: Vec<DuplicateGroup> = groups.into_iter()
+        .map(|group| {
+            let size = group[0].size;
+            let mut paths: Vec<PathBuf> = group.into_iter().map(|candidate| candidate.path).collect();
+            paths.sort();
+            DuplicateGroup { size, paths }
+        })
+        .collect();
+    duplicates.sort_by(|a, b| b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.paths.cmp(&b.paths)));
+    skipped.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(Duplicates { groups: duplicates, skipped })
+}
+
+/// split every group by the hash of its members, dropping the members left without a match and moving the ones
+/// that could not be read to skipped
+fn refine<F>(groups: Vec<Vec<Candidate>>, hash: F, skipped: &mut Vec<(PathBuf, io::Error)>) -> Vec<Vec<Candidate>>
+    where F: Fn(&Candidate, &mut [u8]) -> io::Result<u64> + Sync {
+    let candidates: Vec<(usize, Candidate)> = groups.into_iter()
+        .enumerate()
+        .flat_map(|(index, group)| group.into_iter().map(move |candidate| (index, candidate)))
+        .collect();
+    let hashes = map_parallel(&candidates, num_cpus::get(), BUFFER_SIZE, |(_, candidate), buffer| hash(candidate, buffer));
+
+    let mut refined: HashMap<(usize, u64), Vec<Candidate>> = HashMap::new();
+    for ((index, candidate), hash) in candidates.into_iter().zip(hashes) {
+        match hash {
+            Ok(hash) => refined.entry((index, hash)).or_default().push(candidate),
+            Err(error) => skipped.push((candidate.path, error)),
+        }
+    }
+    refined.into_values().filter(|group| group.len() > 1).collect()
+}
+
+/// split a group whose members hashed alike into groups of files with really identical bytes
+fn confirm(mut group: Vec<Candidate>, skipped: &mut Vec<(PathBuf, io::Error)>) -> Vec<Vec<Candidate>> {
+    let mut confirmed = Vec::new();
+    // kept reversed, so the next pivot pops off the end in the original order
+    group.reverse();
+    while let Some(pivot) = group.pop() {
+        let mut same = vec![pivot];
+        let mut rest = Vec::new();
+        let mut candidates = group.drain(..).rev();
+        let mut pivot_error = None;
+        for candidate in candidates.by_ref() {
+            match same_content(&same[0].path, &candidate.path) {
+                Ok(true) => same.push(candidate),
+                Ok(false) => rest.push(candidate),
+                Err((Side::Second, error)) => skipped.push((candidate.path, error)),
+                // the pivot itself is unreadable, which says nothing about the others
+                Err((Side::First, error)) => {
+                    rest.push(candidate);
+                    pivot_error = Some(error);
+                    break;
+                }
+            }
+        }
+        rest.extend(candidates);
+        match pivot_error {
+            Some(error) => {
+                let mut same = same.into_iter();
+                skipped.push((same.next().expect("the pivot").path, error));
+                rest.splice(0..0, same);
+            }
+            None if same.len() > 1 => confirmed.push(same),
+            None => {}
+        }
+        rest.reverse();
+        group = rest;
+    }
+    confirmed
+}
+
+/// which of two compared files an error came from
+enum Side {
+    First,
+    Second,
+}
+
+fn same_content(first: &Path, second: &Path) -> Result<bool, (Side, io::Error)> {
+    let mut first = File::open(first).map_err(|error| (Side::First, error))?;
+    let mut second = File::open(second).map_err(|error| (Side::Second, error))?;
+    let (mut first_buffer, mut second_buffer) = (vec![0u8; BUFFER_SIZE], vec![0u8; BUFFER_SIZE]);
+    loop {
+        let readed = read_full(&mut first, &mut first_buffer).map_err(|error| (Side::First, error))?;
+        let other = read_full(&mut second, &mut second_buffer).map_err(|error| (Side::Second, error))?;
+        if readed != other || first_buffer[..readed] != second_buffer[..readed] {
+            return Ok(false);
+        }
+        if readed == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// fill buffer unless the file ends first, returning how much was read
+fn read_full(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut readed = 0;
+    while readed < buffer.len() {
+        match file.read(&mut buffer[readed..]) {
+            Ok(0) => break,
+            Ok(count) => readed += count,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(readed)
+}
+
+fn partial_hash(candidate: &Candidate, buffer: &mut [u8]) -> io::Result<u64> {
+    let mut file = File::open(&candidate.path)?;
+    let mut hasher = DefaultHasher::new();
+    let head = (candidate.size as usize).min(BLOCK_SIZE);
+    file.read_exact(&mut buffer[..head])?;
+    hasher.write(&buffer[..head]);
+    if candidate.size > BLOCK_SIZE as u64 {
+        let tail = (candidate.size - BLOCK_SIZE as u64).max(BLOCK_SIZE as u64);
+        let length = (candidate.size - tail) as usize;
+        file.seek(SeekFrom::Start(tail))?;
+        file.read_exact(&mut buffer[..length])?;
+        hasher.write(&buffer[..length]);
+    }
+    Ok(hasher.finish())
+}
+
+fn full_hash(candidate: &Candidate, buffer: &mut [u8]) -> io::Result<u64> {
+    let mut file = File::open(&candidate.path)?;
+    let mut hasher = DefaultHasher::new();
+    loop {
+        let readed = file.read(buffer)?;
+        if readed == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buffer[..readed]);
+    }
+}
+
+#[test]
+fn find_duplicates_test() {
+    use std::fs;
+
+    let root = crate::directory_size::directory_size::test_dir("dupes");
+    fs::create_dir_all(root.join("nested")).unwrap();
+    let content: Vec<u8> = (0..20000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(root.join("a.bin"), &content).unwrap();
+    fs::write(root.join("nested/b.bin"), &content).unwrap();
+    fs::hard_link(root.join("a.bin"), root.join("a_link.bin")).unwrap();
+    let mut middle = content.clone();
+    middle[10000] ^= 0xff;
+    fs::write(root.join("middle.bin"), &middle).unwrap();
+    fs::write(root.join("small1.txt"), "hello rust").unwrap();
+    fs::write(root.join("small2.txt"), "hello rust").unwrap();
+    fs::write(root.join("other.txt"), "hello java").unwrap();
+    fs::write(root.join("empty1"), "").unwrap();
+    fs::write(root.join("empty2"), "").unwrap();
+
+    let duplicates = find_duplicates(root.clone()).unwrap();
+    assert!(duplicates.skipped.is_empty());
+    let groups = duplicates.groups;
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].size, 20000);
+    assert_eq!(groups[0].paths.len(), 2);
+    assert!(groups[0].paths.contains(&root.join("nested/b.bin")));
+    assert!(!groups[0].paths.contains(&root.join("middle.bin")));
+    assert_eq!(groups[0].reclaimable(), 20000);
+    assert_eq!(groups[1].paths, vec![root.join("small1.txt"), root.join("small2.txt")]);
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn confirm_skip_test() {
+    use std::fs;
+
+    let root = crate::directory_size::directory_size::test_dir("dupes_confirm");
+    fs::create_dir_all(&root).unwrap();
+    for (name, content) in [("a", "same bytes"), ("b", "same bytes"), ("c", "diff bytes"), ("d", "diff bytes")] {
+        fs::write(root.join(name), content).unwrap();
+    }
+    let candidate = |name: &str| Candidate { path: root.join(name), size: 10 };
+
+    // as if every member had collided on one hash
+    let mut skipped = Vec::new();
+    let group = vec![candidate("a"), candidate("c"), candidate("b"), candidate("gone"), candidate("d")];
+    let groups: Vec<Vec<PathBuf>> = confirm(group, &mut skipped).into_iter()
+        .map(|group| group.into_iter().map(|candidate| candidate.path).collect())
+        .collect();
+    assert_eq!(groups, vec![vec![root.join("a"), root.join("b")], vec![root.join("c"), root.join("d")]]);
+    assert_eq!(skipped.len(), 1);
+
+    // an unreadable first member is the one skipped, the rest are still compared with each other
+    let mut skipped = Vec::new();
+    let group = vec![candidate("gone"), candidate("a"), candidate("c"), candidate("b")];
+    let groups = confirm(group, &mut skipped);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].iter().map(|candidate| candidate.path.clone()).collect::<Vec<_>>(), [root.join("a"), root.join("b")]);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].0, root.join("gone"));
+
+    // a file that vanished before hashing is reported, the others still grouped
+    let mut skipped = Vec::new();
+    let groups = refine(vec![vec![candidate("a"), candidate("gone"), candidate("b")]], full_hash, &mut skipped);
+    assert_eq!((groups.len(), groups[0].len()), (1, 2));
+    assert_eq!(skipped[0].0, root.join("gone"));
+    assert_eq!(skipped[0].1.kind(), io::ErrorKind::NotFound);
+    fs::remove_dir_all(root).unwrap();
+}
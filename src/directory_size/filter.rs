@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::directory_size::directory_size::walk;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// upper bound in days and label of every mtime bucket, files older than the last one go to `OLDEST_BUCKET`
+const AGE_BUCKETS: [(u64, &str); 4] = [(1, "0-1 days"), (7, "1-7 days"), (30, "7-30 days"), (365, "30-365 days")];
+const OLDEST_BUCKET: &str = "365+ days";
+
+/// Which regular files take part in a report.
+///
+/// Globs support `*` and `?` within one path component and `**` across components. A glob without a `/` is
+/// matched against the file name, otherwise against the path relative to the scanned root. A file is kept
+/// when it matches any include glob (or there are none) and no exclude glob.
+#[derive(Debug, Default)]
+pub struct Filter {
+    includes: Vec<String>,
+    excludes: Vec<String>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_before: Option<SystemTime>,
+    modified_after: Option<SystemTime>,
+}
+
+impl Filter {
+    pub fn new() -> Filter {
+        Filter::default()
+    }
+
+    pub fn include(mut self, glob: &str) -> Filter {
+        self.includes.push(glob.to_string());
+        self
+    }
+
+    pub fn exclude(mut self, glob: &str) -> Filter {
+        self.excludes.push(glob.to_string());
+        self
+    }
+
+    pub fn min_size(mut self, size: u64) -> Filter {
+        self.min_size = Some(size);
+        self
+    }
+
+    pub fn max_size(mut self, size: u64) -> Filter {
+        self.max_size = Some(size);
+        self
+    }
+
+    pub fn modified_before(mut self, time: SystemTime) -> Filter {
+        self.modified_before = Some(time);
+        self
+    }
+
+    pub fn modified_after(mut self, time: SystemTime) -> Filter {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// keep only files last modified more than days ago
+    pub fn older_than_days(self, days: u64) -> Filter {
+        // so many days that the time cannot be represented keeps nothing but files from before the epoch
+        let time = SystemTime::now().checked_sub(Duration::from_secs(days.saturating_mul(DAY)));
+        self.modified_before(time.unwrap_or(SystemTime::UNIX_EPOCH))
+    }
+
+    pub fn matches(&self, relative: &Path, metadata: &Metadata) -> bool {
+        let glob_matches = |glob: &String| glob_match(glob, relative);
+        if !self.includes.is_empty() && !self.includes.iter().any(glob_matches) {
+            return false;
+        }
+        if self.excludes.iter().any(glob_matches) {
+            return false;
+        }
+        if self.min_size.is_some_and(|size| metadata.len() < size)
+            || self.max_size.is_some_and(|size| metadata.len() > size) {
+            return false;
+        }
+        if self.modified_before.is_some() || self.modified_after.is_some() {
+            let modified = match metadata.modified() {
+                Ok(modified) => modified,
+                Err(_) => return false,
+            };
+            if self.modified_before.is_some_and(|time| modified >= time)
+                || self.modified_after.is_some_and(|time| modified <= time) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// what the files of a report are grouped by
+#[derive(Debug, Clone, Copy)]
+pub enum GroupBy {
+    Extension,
+    Owner,
+    Age,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GroupTotal {
+    pub key: String,
+    pub files: u64,
+    pub size: u64,
+}
+
+/// sum the size of the regular files under path kept by filter, per group and biggest group first
+pub fn aggregate(path: PathBuf, filter: &Filter, group_by: GroupBy) -> io::Result<Vec<GroupTotal>> {
+    let now = SystemTime::now();
+    let root = path.clone();
+    let mut totals: HashMap<String, GroupTotal> = HashMap::new();
+    walk(path, |entry, metadata| {
+        let relative = entry.strip_prefix(&root).unwrap_or(entry);
+        if !metadata.is_file() || !filter.matches(relative, metadata) {
+            return;
+        }
+        let key = group_key(entry, metadata, group_by, now);
+        let total = totals.entry(key.clone()).or_insert(GroupTotal { key, files: 0, size: 0 });
+        total.files += 1;
+        total.size += metadata.len();
+    })?;
+
+    let mut totals: Vec<GroupTotal> = totals.into_values().collect();
+    totals.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.key.cmp(&b.key)));
+    Ok(totals)
+}
+
+fn group_key(path: &Path, metadata: &Metadata, group_by: GroupBy, now: SystemTime) -> String {
+    match group_by {
+        GroupBy::Extension => match path.extension() {
+            Some(extension) => format!(".{}", extension.to_string_lossy()),
+            None => "(none)".to_string(),
+        },
+        GroupBy::Owner => metadata.uid().to_string(),
+        GroupBy::Age => {
+            let age = metadata.modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            AGE_BUCKETS.iter()
+                .find(|(days, _)| age < Duration::from_secs(days * DAY))
+                .map_or(OLDEST_BUCKET, |(_, label)| label)
+                .to_string()
+        }
+    }
+}
+
+fn glob_match(glob: &str, path: &Path) -> bool {
+    let text = if glob.contains('/') {
+        path.to_string_lossy()
+    } else {
+        path.file_name().unwrap_or_default().to_string_lossy()
+    };
+    let pattern: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Char(char),
+    /// `?`, one character other than `/`
+    One,
+    /// `*`, any run of characters other than `/`
+    Star,
+    /// `**`, any run of characters
+    DoubleStar,
+    /// `**/`, nothing or any run of characters ending with `/`
+    Directories,
+}
+
+fn tokens(pattern: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::with_capacity(pattern.len());
+    let mut index = 0;
+    while index < pattern.len() {
+        let (token, length) = match &pattern[index..] {
+            ['*', '*', '/', ..] => (Token::Directories, 3),
+            ['*', '*', ..] => (Token::DoubleStar, 2),
+            ['*', ..] => (Token::Star, 1),
+            ['?', ..] => (Token::One, 1),
+            [c, ..] => (Token::Char(*c), 1),
+            [] => unreachable!(),
+        };
+        tokens.push(token);
+        index += length;
+    }
+    tokens
+}
+
+/// match by carrying the set of text positions the pattern so far can end at through every token, which takes
+/// pattern length times text length steps however many stars there are
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    let mut reachable = vec![false; text.len() + 1];
+    reachable[0] = true;
+    for token in tokens(pattern) {
+        let mut next = vec![false; text.len() + 1];
+        let mut carried = false;
+        for position in 0..=text.len() {
+            let previous = position.checked_sub(1).map(|index| text[index]);
+            next[position] = match token {
+                Token::Char(c) => previous == Some(c) && reachable[position - 1],
+                Token::One => previous.is_some_and(|c| c != '/') && reachable[position - 1],
+                Token::Star => {
+                    carried = reachable[position] || (carried && previous != Some('/'));
+                    carried
+                }
+                Token::DoubleStar => {
+                    carried |= reachable[position];
+                    carried
+                }
+                Token::Directories => {
+                    let matched = reachable[position] || (carried && previous == Some('/'));
+                    carried |= reachable[position];
+                    matched
+                }
+            };
+        }
+        reachable = next;
+    }
+    reachable[text.len()]
+}
+
+#[test]
+fn glob_match_test() {
+    assert!(glob_match("*.o", Path::new("target/debug/main.o")));
+    assert!(!glob_match("*.o", Path::new("target/debug/main.rs")));
+    assert!(glob_match("target/**/*.o", Path::new("target/debug/deps/main.o")));
+    assert!(glob_match("target/**/*.o", Path::new("target/main.o")));
+    assert!(!glob_match("target/*.o", Path::new("target/debug/main.o")));
+    assert!(glob_match("ma?n.*", Path::new("main.rs")));
+    assert!(!glob_match("src/*", Path::new("target/src/main.rs")));
+    assert!(glob_match("**/*.o", Path::new("main.o")));
+    assert!(glob_match("a/**", Path::new("a/b/c")));
+    assert!(!glob_match("a/*", Path::new("a/b/c")));
+    assert!(glob_match("*/b", Path::new("a/b")));
+
+    // many stars stay fast on a text they almost match
+    let text = format!("{}c", "a".repeat(200));
+    assert!(!glob_match("*a*a*a*a*a*a*a*a*a*a*b", Path::new(&text)));
+    assert!(glob_match("**a**a**a**a**c", Path::new(&format!("x/{}", text))));
+}
+
+#[test]
+fn aggregate_test() {
+    use std::fs;
+    use std::fs::File;
+    use crate::directory_size::directory_size::directory_size_filtered;
+
+    let root = crate::directory_size::directory_size::test_dir("filter");
+    fs::create_dir_all(root.join("target/debug")).unwrap();
+    fs::write(root.join("target/debug/old.o"), [0u8; 300]).unwrap();
+    fs::write(root.join("target/debug/new.o"), [0u8; 200]).unwrap();
+    fs::write(root.join("target/debug/lib.rlib"), [0u8; 100]).unwrap();
+    fs::write(root.join("target/debug/tiny.o"), [0u8; 1]).unwrap();
+    fs::write(root.join("notes"), [0u8; 50]).unwrap();
+    let old = SystemTime::now() - Duration::from_secs(40 * DAY);
+    File::options().write(true).open(root.join("target/debug/old.o")).unwrap().set_modified(old).unwrap();
+
+    let by_extension = aggregate(root.clone(), &Filter::new().min_size(2), GroupBy::Extension).unwrap();
+    assert_eq!(by_extension, vec![
+        GroupTotal { key: ".o".to_string(), files: 2, size: 500 },
+        GroupTotal { key: ".rlib".to_string(), files: 1, size: 100 },
+        GroupTotal { key: "(none)".to_string(), files: 1, size: 50 },
+    ]);
+
+    let old_objects = Filter::new().include("target/**").include("*.o").exclude("*.rlib").older_than_days(30);
+    let by_age = aggregate(root.clone(), &old_objects, GroupBy::Age).unwrap();
+    assert_eq!(by_age, vec![GroupTotal { key: "30-365 days".to_string(), files: 1, size: 300 }]);
+
+    let by_owner = aggregate(root.clone(), &Filter::new().max_size(100), GroupBy::Owner).unwrap();
+    assert_eq!(by_owner.len(), 1);
+    assert_eq!(by_owner[0].files, 3);
+
+    let recent = Filter::new().modified_after(SystemTime::now() - Duration::from_secs(DAY));
+    assert_eq!(directory_size_filtered(root.clone(), &recent).unwrap(), 351);
+    assert_eq!(directory_size_filtered(root.clone(), &Filter::new().include("*.o")).unwrap(), 501);
+    assert_eq!(directory_size_filtered(root.clone(), &Filter::new().older_than_days(u64::MAX)).unwrap(), 0);
+    fs::remove_dir_all(root).unwrap();
+}
\ No newline at end of file
@@ -1 +1,5 @@
 mod directory_size;
+mod snapshot;
+mod dupes;
+mod filter;
+pub mod browser;
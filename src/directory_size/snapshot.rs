@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::directory_size::directory_size::directory_sizes;
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"DSNP";
+const SNAPSHOT_VERSION: u8 = 1;
+/// longest path accepted when reading, far above what any file system allows, so a corrupt length cannot exhaust memory
+const MAX_PATH_LENGTH: usize = 64 * 1024;
+
+/// Per-directory sizes of a tree at one point in time, keyed by the path relative to the scanned root
+/// (the root itself is the empty path), so that two scans of the same tree can be compared.
+///
+/// The file format is compact little-endian binary:
+/// `"DSNP" version:u8 count:u64 (path_len:u32 path_bytes size:u64)*`
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    sizes: BTreeMap<PathBuf, u64>,
+}
+
+/// How the size of one directory changed between two snapshots, `None` when it did not exist
+#[derive(Debug, PartialEq)]
+pub struct SizeDelta {
+    pub path: PathBuf,
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+}
+
+impl SizeDelta {
+    pub fn delta(&self) -> i64 {
+        self.new_size.unwrap_or(0) as i64 - self.old_size.unwrap_or(0) as i64
+    }
+}
+
+impl Snapshot {
+    pub fn scan(root: PathBuf) -> io::Result<Snapshot> {
+        let sizes = directory_sizes(root.clone())?
+            .into_iter()
+            .map(|(path, size)| {
+                let relative = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+                (relative, size)
+            })
+            .collect();
+        Ok(Snapshot { sizes })
+    }
+
+    pub fn sizes(&self) -> &BTreeMap<PathBuf, u64> {
+        &self.sizes
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Snapshot> {
+        Snapshot::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&[SNAPSHOT_VERSION])?;
+        writer.write_all(&(self.sizes.len() as u64).to_le_bytes())?;
+        for (path, size) in &self.sizes {
+            let bytes = path.as_os_str().as_bytes();
+            writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            writer.write_all(bytes)?;
+            writer.write_all(&size.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Snapshot> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if &magic != SNAPSHOT_MAGIC || version[0] != SNAPSHOT_VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a directory size snapshot"));
+        }
+
+        let mut u32_bytes = [0u8; 4];
+        let mut u64_bytes = [0u8; 8];
+        reader.read_exact(&mut u64_bytes)?;
+        let count = u64::from_le_bytes(u64_bytes);
+        let mut sizes = BTreeMap::new();
+        for _ in 0..count {
+            reader.read_exact(&mut u32_bytes)?;
+            let length = u32::from_le_bytes(u32_bytes) as usize;
+            if length > MAX_PATH_LENGTH {
+                return Err(io::Error::new(ErrorKind::InvalidData, "snapshot path is too long"));
+            }
+            let mut path = vec![0u8; length];
+            reader.read_exact(&mut path)?;
+            reader.read_exact(&mut u64_bytes)?;
+            sizes.insert(PathBuf::from(OsStr::from_bytes(&path)), u64::from_le_bytes(u64_bytes));
+        }
+        Ok(Snapshot { sizes })
+    }
+
+    /// the subtrees whose size changed from this snapshot to newer, biggest growth first and biggest shrink last
+    pub fn diff(&self, newer: &Snapshot) -> Vec<SizeDelta> {
+        let mut paths: Vec<&PathBuf> = self.sizes.keys().chain(newer.sizes.keys()).collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut deltas: Vec<SizeDelta> = paths.into_iter()
+            .map(|path| SizeDelta {
+                path: path.clone(),
+                old_size: self.sizes.get(path).copied(),
+                new_size: newer.sizes.get(path).copied(),
+            })
+            .filter(|delta| delta.old_size != delta.new_size)
+            .collect();
+        deltas.sort_by(|a, b| b.delta().cmp(&a.delta()).then_with(|| a.path.cmp(&b.path)));
+        deltas
+    }
+}
+
+#[cfg(test)]
+fn snapshot_of(sizes: &[(&str, u64)]) -> Snapshot {
+    Snapshot {
+        sizes: sizes.iter().map(|(path, size)| (PathBuf::from(path), *size)).collect(),
+    }
+}
+
+#[test]
+fn snapshot_round_trip_test() {
+    let root = crate::directory_size::directory_size::test_dir("snapshot");
+    std::fs::create_dir_all(root.join("cache/objects")).unwrap();
+    std::fs::write(root.join("cache/objects/a.o"), [0u8; 4096]).unwrap();
+
+    let snapshot = Snapshot::scan(root.clone()).unwrap();
+    assert!(snapshot.sizes().contains_key(Path::new("")));
+    assert!(snapshot.sizes().contains_key(Path::new("cache/objects")));
+
+    let file = root.join("snapshot.bin");
+    snapshot.save(&file).unwrap();
+    assert_eq!(Snapshot::load(&file).unwrap(), snapshot);
+
+    let error = Snapshot::read_from(&mut &b"JUNKJUNK"[..]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    // a huge count and path length fail on the length instead of allocating
+    let mut corrupt = b"DSNP\x01".to_vec();
+    corrupt.extend_from_slice(&u64::MAX.to_le_bytes());
+    corrupt.extend_from_slice(&u32::MAX.to_le_bytes());
+    let error = Snapshot::read_from(&mut &corrupt[..]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn snapshot_diff_test() {
+    let old = snapshot_of(&[("", 1000), ("logs", 100), ("target", 800), ("tmp", 100)]);
+    let new = snapshot_of(&[("", 5050), ("logs", 50), ("target", 5000), ("cache", 0)]);
+
+    let deltas = old.diff(&new);
+    let summary: Vec<(&str, i64)> = deltas.iter()
+        .map(|delta| (delta.path.to_str().unwrap(), delta.delta()))
+        .collect();
+    assert_eq!(summary, vec![("target", 4200), ("", 4050), ("cache", 0), ("logs", -50), ("tmp", -100)]);
+    assert_eq!(deltas[2].old_size, None);
+    assert_eq!(deltas[4].new_size, None);
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use std::io;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+/// A `RandomAccessSource` over bytes already in memory: `Vec<u8>`, `Arc<[u8]>`, `&'static [u8]` or anything
+/// else that is `AsRef<[u8]>`.
+pub struct ArrayRandomAccessSource<B: AsRef<[u8]>> {
+    bytes: B,
+}
+
+impl<B: AsRef<[u8]>> ArrayRandomAccessSource<B> {
+    pub fn new(bytes: B) -> ArrayRandomAccessSource<B> {
+        ArrayRandomAccessSource { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bytes
+    }
+}
+
+impl<B: AsRef<[u8]>> RandomAccessSource for ArrayRandomAccessSource<B> {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        Ok(usize::try_from(position).ok().and_then(|index| self.bytes.as_ref().get(index)).copied())
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        let source = self.bytes.as_ref();
+        if position >= source.len() as u64 {
+            return Ok(0);
+        }
+        let start = position as usize;
+        let count = bytes.len().min(source.len() - start);
+        bytes[..count].copy_from_slice(&source[start..start + count]);
+        Ok(count)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.bytes.as_ref().len() as u64)
+    }
+}
+
+#[test]
+fn array_conformance_test() {
+    use std::sync::Arc;
+    use crate::example::random_access_source::{check_conformance, conformance_bytes};
+
+    let expected = conformance_bytes();
+    check_conformance(&ArrayRandomAccessSource::new(expected.clone()), &expected);
+    let shared: Arc<[u8]> = Arc::from(expected.clone());
+    check_conformance(&ArrayRandomAccessSource::new(shared), &expected);
+    let fixture: &'static [u8] = Box::leak(expected.clone().into_boxed_slice());
+    check_conformance(&ArrayRandomAccessSource::new(fixture), &expected);
+    check_conformance(&ArrayRandomAccessSource::new(Vec::new()), &[]);
+}
+
+#[test]
+fn array_random_access_test() {
+    let source = ArrayRandomAccessSource::new(&b"hello rust"[..]);
+    assert_eq!(source.get(6).unwrap(), Some(b'r'));
+    assert_eq!(source.get(10).unwrap(), None);
+    let mut bytes = [0u8; 8];
+    assert_eq!(source.get_by_bytes(6, &mut bytes).unwrap(), 4);
+    assert_eq!(&bytes[..4], b"rust");
+    assert_eq!(source.into_inner(), b"hello rust");
+}
\ No newline at end of file
@@ -0,0 +1,204 @@
+use std::io;
+use std::io::ErrorKind;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, ready};
+use futures::FutureExt;
+
+use crate::example::random_access_source::{FileRandomAccessSouce, RandomAccessSource};
+
+/// The async counterpart of `RandomAccessSource`, with the same end of source rules.
+///
+/// Reads hand back owned buffers so that the work can move to another thread while the caller awaits it. The futures
+/// only rely on the `futures` crate and run on any executor.
+pub trait AsyncRandomAccessSource: Send + Sync {
+    /// get a u8 from specified position, `None` at or after the end of the source
+    fn get(&self, position: u64) -> BoxFuture<'_, io::Result<Option<u8>>>;
+
+    /// up to length bytes from position, fewer only when the source ends first
+    fn read_at(&self, position: u64, length: usize) -> BoxFuture<'_, io::Result<Vec<u8>>>;
+
+    /// length of this source
+    fn length(&self) -> BoxFuture<'_, io::Result<u64>>;
+
+    /// exactly length bytes from position, failing with `UnexpectedEof` when the source ends first
+    fn read_fully_at(&self, position: u64, length: usize) -> BoxFuture<'_, io::Result<Vec<u8>>> {
+        self.read_at(position, length)
+            .map(move |result| match result {
+                Ok(bytes) if bytes.len() < length => Err(io::Error::new(
+                    ErrorKind::UnexpectedEof, format!("source ended at {}", position + bytes.len() as u64))),
+                other => other,
+            })
+            .boxed()
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of threads running blocking calls off the executor threads.
+pub struct BlockingPool {
+    sender: Mutex<mpsc::Sender<Job>>,
+}
+
+impl BlockingPool {
+    pub fn new(threads: usize) -> BlockingPool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                // the lock is released before running the job, so the other threads keep taking jobs
+                let job = receiver.lock().unwrap().recv();
+                match job {
+                    // a panicking job drops its result sender, which fails its future, and the thread lives on
+                    Ok(job) => {
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                    Err(_) => return,
+                }
+            });
+        }
+        BlockingPool { sender: Mutex::new(sender) }
+    }
+
+    /// run call on the pool, the future resolves with its result
+    pub fn spawn<T, F>(&self, call: F) -> BoxFuture<'static, io::Result<T>>
+        where T: Send + 'static, F: FnOnce() -> T + Send + 'static {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = sender.send(call());
+        });
+        if self.sender.lock().unwrap().send(job).is_err() {
+            return ready(Err(io::Error::other("blocking pool is shut down"))).boxed();
+        }
+        receiver
+            .map(|result| result.map_err(|_| io::Error::other("blocking call panicked")))
+            .boxed()
+    }
+}
+
+/// Adapts any `RandomAccessSource` to `AsyncRandomAccessSource`.
+///
+/// Without a pool the source is called inline, which suits sources that never block such as in-memory ones. With a
+/// pool every call runs on one of its threads.
+pub struct SyncRandomAccessAdapter<S: RandomAccessSource + Send + Sync + 'static> {
+    source: Arc<S>,
+    pool: Option<Arc<BlockingPool>>,
+}
+
+impl<S: RandomAccessSource + Send + Sync + 'static> SyncRandomAccessAdapter<S> {
+    pub fn new(source: S) -> SyncRandomAccessAdapter<S> {
+        SyncRandomAccessAdapter { source: Arc::new(source), pool: None }
+    }
+
+    pub fn offloaded(source: S, pool: Arc<BlockingPool>) -> SyncRandomAccessAdapter<S> {
+        SyncRandomAccessAdapter { source: Arc::new(source), pool: Some(pool) }
+    }
+
+    fn call<T, F>(&self, call: F) -> BoxFuture<'_, io::Result<T>>
+        where T: Send + 'static, F: FnOnce(&S) -> io::Result<T> + Send + 'static {
+        match &self.pool {
+            Some(pool) => {
+                let source = Arc::clone(&self.source);
+                pool.spawn(move || call(&source)).map(|result| result.and_then(|inner| inner)).boxed()
+            }
+            None => ready(call(&self.source)).boxed(),
+        }
+    }
+}
+
+impl<S: RandomAccessSource + Send + Sync + 'static> AsyncRandomAccessSource for SyncRandomAccessAdapter<S> {
+    fn get(&self, position: u64) -> BoxFuture<'_, io::Result<Option<u8>>> {
+        self.call(move |source| source.get(position))
+    }
+
+    fn read_at(&self, position: u64, length: usize) -> BoxFuture<'_, io::Result<Vec<u8>>> {
+        self.call(move |source| {
+            let mut bytes = vec![0u8; length];
+            let readed = source.get_by_bytes(position, &mut bytes)?;
+            bytes.truncate(readed);
+            Ok(bytes)
+        })
+    }
+
+    fn length(&self) -> BoxFuture<'_, io::Result<u64>> {
+        self.call(|source| source.length())
+    }
+}
+
+/// A file read on a `BlockingPool`, so awaiting its reads never blocks the executor.
+pub struct AsyncFileRandomAccessSource {
+    inner: SyncRandomAccessAdapter<FileRandomAccessSouce>,
+}
+
+impl AsyncFileRandomAccessSource {
+    pub fn new(path: &str, pool: Arc<BlockingPool>) -> io::Result<AsyncFileRandomAccessSource> {
+        let file = FileRandomAccessSouce::new(path)?;
+        Ok(AsyncFileRandomAccessSource { inner: SyncRandomAccessAdapter::offloaded(file, pool) })
+    }
+}
+
+impl AsyncRandomAccessSource for AsyncFileRandomAccessSource {
+    fn get(&self, position: u64) -> BoxFuture<'_, io::Result<Option<u8>>> {
+        self.inner.get(position)
+    }
+
+    fn read_at(&self, position: u64, length: usize) -> BoxFuture<'_, io::Result<Vec<u8>>> {
+        self.inner.read_at(position, length)
+    }
+
+    fn length(&self) -> BoxFuture<'_, io::Result<u64>> {
+        self.inner.length()
+    }
+}
+
+#[test]
+fn async_file_random_access_test() {
+    use futures::executor::block_on;
+    use futures::future::join_all;
+    use crate::example::random_access_source::{conformance_bytes, test_file};
+
+    let bytes = conformance_bytes();
+    let path = test_file("async", &bytes);
+    let pool = Arc::new(BlockingPool::new(4));
+    let source = AsyncFileRandomAccessSource::new(&path, pool).unwrap();
+
+    block_on(async {
+        assert_eq!(source.length().await.unwrap(), 10000);
+        assert_eq!(source.get(9999).await.unwrap(), Some(bytes[9999]));
+        assert_eq!(source.get(10000).await.unwrap(), None);
+
+        let reads = join_all((0..10u64).map(|chunk| source.read_at(chunk * 1000, 1000))).await;
+        let joined: Vec<u8> = reads.into_iter().flat_map(|read| read.unwrap()).collect();
+        assert_eq!(joined, bytes);
+
+        assert_eq!(source.read_at(9990, 100).await.unwrap(), &bytes[9990..]);
+        assert_eq!(source.read_fully_at(9990, 10).await.unwrap(), &bytes[9990..]);
+        let error = source.read_fully_at(9990, 11).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    });
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn sync_adapter_test() {
+    use futures::executor::block_on;
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let source = SyncRandomAccessAdapter::new(ArrayRandomAccessSource::new(&b"hello rust"[..]));
+    block_on(async {
+        assert_eq!(source.get(0).await.unwrap(), Some(b'h'));
+        assert_eq!(source.read_at(6, 10).await.unwrap(), b"rust");
+        assert_eq!(source.length().await.unwrap(), 10);
+    });
+
+    // the pool threads report a panicking call instead of hanging the caller
+    let pool = BlockingPool::new(1);
+    let panicked = block_on(pool.spawn(|| -> u8 { panic!("boom") }));
+    assert!(panicked.is_err());
+    assert_eq!(block_on(pool.spawn(|| 42)).unwrap(), 42);
+}
\ No newline at end of file
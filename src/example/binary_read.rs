@@ -0,0 +1,189 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+/// longest LEB128 encoding of a u64
+const MAX_VARINT_LENGTH: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+#[derive(Debug)]
+pub enum BinaryReadError {
+    /// the source ended at position, before the value starting at start was complete
+    UnexpectedEof { start: u64, position: u64 },
+    /// reading the source failed at position
+    Io { position: u64, error: io::Error },
+    /// the string starting at position is not valid UTF-8
+    InvalidUtf8 { position: u64 },
+    /// the varint starting at position does not fit in 64 bits
+    VarintOverflow { position: u64 },
+}
+
+impl BinaryReadError {
+    /// the position reading stopped at
+    pub fn position(&self) -> u64 {
+        match self {
+            BinaryReadError::UnexpectedEof { position, .. } => *position,
+            BinaryReadError::Io { position, .. } => *position,
+            BinaryReadError::InvalidUtf8 { position } => *position,
+            BinaryReadError::VarintOverflow { position } => *position,
+        }
+    }
+}
+
+impl fmt::Display for BinaryReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryReadError::UnexpectedEof { start, position } =>
+                write!(f, "unexpected end of source at {} reading the value at {}", position, start),
+            BinaryReadError::Io { position, error } => write!(f, "read error at {}: {}", position, error),
+            BinaryReadError::InvalidUtf8 { position } => write!(f, "invalid UTF-8 string at {}", position),
+            BinaryReadError::VarintOverflow { position } => write!(f, "varint at {} overflows 64 bits", position),
+        }
+    }
+}
+
+impl Error for BinaryReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BinaryReadError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! read_number {
+    ($($name:ident: $type:ty),*) => {$(
+        fn $name(&self, position: u64, endian: Endian) -> Result<$type, BinaryReadError> {
+            let bytes = self.read_array(position)?;
+            Ok(match endian {
+                Endian::Big => <$type>::from_be_bytes(bytes),
+                Endian::Little => <$type>::from_le_bytes(bytes),
+            })
+        }
+    )*};
+}
+
+/// Typed reads at an offset of any `RandomAccessSource`.
+pub trait BinaryRead: RandomAccessSource {
+    /// fill bytes from position, failing unless all of them could be read
+    fn read_exact_at(&self, position: u64, bytes: &mut [u8]) -> Result<(), BinaryReadError> {
+        self.read_fully_at(position, bytes).map_err(|error| match error.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                // the source only says it ended, its length tells where
+                let end = position.saturating_add(bytes.len() as u64);
+                let ended = self.length().map_or(position, |length| length.clamp(position, end));
+                BinaryReadError::UnexpectedEof { start: position, position: ended }
+            }
+            _ => BinaryReadError::Io { position, error },
+        })
+    }
+
+    fn read_array<const N: usize>(&self, position: u64) -> Result<[u8; N], BinaryReadError> {
+        let mut bytes = [0u8; N];
+        self.read_exact_at(position, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_u8(&self, position: u64) -> Result<u8, BinaryReadError> {
+        Ok(self.read_array::<1>(position)?[0])
+    }
+
+    fn read_i8(&self, position: u64) -> Result<i8, BinaryReadError> {
+        Ok(self.read_u8(position)? as i8)
+    }
+
+    read_number!(read_u16: u16, read_u32: u32, read_u64: u64,
+                 read_i16: i16, read_i32: i32, read_i64: i64,
+                 read_f32: f32, read_f64: f64);
+
+    /// an unsigned LEB128 varint, with the number of bytes it took
+    fn read_varint(&self, position: u64) -> Result<(u64, usize), BinaryReadError> {
+        let mut value: u64 = 0;
+        for index in 0..MAX_VARINT_LENGTH {
+            let byte = self.read_u8(position + index as u64)?;
+            let bits = (byte & 0x7f) as u64;
+            if index == MAX_VARINT_LENGTH - 1 && bits > 1 {
+                break;
+            }
+            value |= bits << (7 * index);
+            if byte & 0x80 == 0 {
+                return Ok((value, index + 1));
+            }
+        }
+        Err(BinaryReadError::VarintOverflow { position })
+    }
+
+    /// a zigzag encoded signed LEB128 varint, with the number of bytes it took
+    fn read_signed_varint(&self, position: u64) -> Result<(i64, usize), BinaryReadError> {
+        let (value, length) = self.read_varint(position)?;
+        Ok(((value >> 1) as i64 ^ -((value & 1) as i64), length))
+    }
+
+    fn read_bytes(&self, position: u64, length: usize) -> Result<Vec<u8>, BinaryReadError> {
+        let mut bytes = vec![0u8; length];
+        self.read_exact_at(position, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// a UTF-8 string of exactly length bytes
+    fn read_string(&self, position: u64, length: usize) -> Result<String, BinaryReadError> {
+        String::from_utf8(self.read_bytes(position, length)?)
+            .map_err(|_| BinaryReadError::InvalidUtf8 { position })
+    }
+}
+
+impl<S: RandomAccessSource + ?Sized> BinaryRead for S {}
+
+#[test]
+fn binary_read_numbers_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let mut bytes = vec![0xffu8, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0];
+    bytes.extend_from_slice(&1.5f64.to_be_bytes());
+    bytes.extend_from_slice(&(-2.25f32).to_le_bytes());
+    let source = ArrayRandomAccessSource::new(bytes);
+
+    assert_eq!(source.read_u8(0).unwrap(), 0xff);
+    assert_eq!(source.read_i8(0).unwrap(), -1);
+    assert_eq!(source.read_u16(1, Endian::Big).unwrap(), 0x1234);
+    assert_eq!(source.read_u16(1, Endian::Little).unwrap(), 0x3412);
+    assert_eq!(source.read_u32(1, Endian::Big).unwrap(), 0x12345678);
+    assert_eq!(source.read_u64(1, Endian::Little).unwrap(), 0xf0debc9a78563412);
+    assert_eq!(source.read_i16(0, Endian::Big).unwrap(), -238);
+    assert_eq!(source.read_i32(0, Endian::Little).unwrap(), 0x563412ff);
+    assert_eq!(source.read_i64(0, Endian::Big).unwrap(), 0xff123456789abcdeu64 as i64);
+    assert_eq!(source.read_f64(9, Endian::Big).unwrap(), 1.5);
+    assert_eq!(source.read_f32(17, Endian::Little).unwrap(), -2.25);
+
+    match source.read_u64(15, Endian::Big) {
+        Err(BinaryReadError::UnexpectedEof { start, position }) => assert_eq!((start, position), (15, 21)),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn binary_read_varint_string_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let source = ArrayRandomAccessSource::new(vec![0xac, 0x02, 0x03, 0x7f, b'r', b'u', b's', b't', 0xc3, 0x28]);
+    assert_eq!(source.read_varint(0).unwrap(), (300, 2));
+    assert_eq!(source.read_signed_varint(2).unwrap(), (-2, 1));
+    assert_eq!(source.read_signed_varint(3).unwrap(), (-64, 1));
+    assert_eq!(source.read_string(4, 4).unwrap(), "rust");
+    assert_eq!(source.read_string(8, 2).unwrap_err().position(), 8);
+    assert_eq!(source.read_string(8, 3).unwrap_err().position(), 10);
+
+    let overflow = ArrayRandomAccessSource::new(vec![0xff; 11]);
+    assert!(matches!(overflow.read_varint(0), Err(BinaryReadError::VarintOverflow { position: 0 })));
+    let max = ArrayRandomAccessSource::new(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    assert_eq!(max.read_varint(0).unwrap(), (u64::MAX, 10));
+    let truncated = ArrayRandomAccessSource::new(vec![0x80, 0x80]);
+    assert_eq!(truncated.read_varint(0).unwrap_err().position(), 2);
+}
\ No newline at end of file
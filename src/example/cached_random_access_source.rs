@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+const DEFAULT_PAGE_SIZE: usize = 4096;
+const DEFAULT_CAPACITY: usize = 256;
+
+struct Page {
+    bytes: Vec<u8>,
+    last_used: u64,
+}
+
+struct Pages {
+    pages: HashMap<u64, Page>,
+    /// page indexes by the tick they were last used at, so the least recently used one is the first entry
+    by_use: BTreeMap<u64, u64>,
+    clock: u64,
+}
+
+impl Pages {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// copy out of the cached page index, marking it as just used
+    fn copy(&mut self, index: u64, offset: usize, bytes: &mut [u8]) -> Option<usize> {
+        let clock = self.tick();
+        let page = self.pages.get_mut(&index)?;
+        self.by_use.remove(&page.last_used);
+        self.by_use.insert(clock, index);
+        page.last_used = clock;
+        Some(copy_from(&page.bytes, offset, bytes))
+    }
+
+    fn insert(&mut self, index: u64, bytes: Vec<u8>, capacity: usize) {
+        // another thread may have read the same page meanwhile
+        if self.pages.contains_key(&index) {
+            return;
+        }
+        if self.pages.len() >= capacity {
+            if let Some((_, oldest)) = self.by_use.pop_first() {
+                self.pages.remove(&oldest);
+            }
+        }
+        let clock = self.tick();
+        self.by_use.insert(clock, index);
+        self.pages.insert(index, Page { bytes, last_used: clock });
+    }
+}
+
+/// A `RandomAccessSource` reading another source in aligned pages and keeping the least recently used ones
+/// evicted first, so reading byte by byte does not cost one read of the wrapped source per byte.
+///
+/// The wrapped source is read without holding the lock, and reads longer than a page go straight to it.
+pub struct CachedRandomAccessSource<S: RandomAccessSource> {
+    source: S,
+    page_size: usize,
+    capacity: usize,
+    pages: Mutex<Pages>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<S: RandomAccessSource> CachedRandomAccessSource<S> {
+    pub fn new(source: S) -> CachedRandomAccessSource<S> {
+        CachedRandomAccessSource::with_page_size(source, DEFAULT_PAGE_SIZE, DEFAULT_CAPACITY)
+            .expect("default cache sizes are valid")
+    }
+
+    /// cache up to capacity pages of page_size bytes
+    pub fn with_page_size(source: S, page_size: usize, capacity: usize) -> io::Result<CachedRandomAccessSource<S>> {
+        if page_size == 0 || capacity == 0 {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "page size and capacity must not be zero"));
+        }
+        Ok(CachedRandomAccessSource {
+            source,
+            page_size,
+            capacity,
+            pages: Mutex::new(Pages { pages: HashMap::new(), by_use: BTreeMap::new(), clock: 0 }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    /// number of page lookups served from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// number of page lookups that read the wrapped source
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// copy the bytes at position out of the page holding it, reading the page first when it is not cached
+    fn read_page(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        let index = position / self.page_size as u64;
+        let offset = (position % self.page_size as u64) as usize;
+        if let Some(count) = self.pages.lock().unwrap().copy(index, offset, bytes) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(count);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let mut page = vec![0u8; self.page_size];
+        let readed = self.source.get_by_bytes(index * self.page_size as u64, &mut page)?;
+        page.truncate(readed);
+        let count = copy_from(&page, offset, bytes);
+        if !page.is_empty() {
+            self.pages.lock().unwrap().insert(index, page, self.capacity);
+        }
+        Ok(count)
+    }
+}
+
+fn copy_from(page: &[u8], offset: usize, bytes: &mut [u8]) -> usize {
+    if offset >= page.len() {
+        return 0;
+    }
+    let count = bytes.len().min(page.len() - offset);
+    bytes[..count].copy_from_slice(&page[offset..offset + count]);
+    count
+}
+
+impl<S: RandomAccessSource> RandomAccessSource for CachedRandomAccessSource<S> {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        let mut buffer: [u8; 1] = [0];
+        match self.read_page(position, &mut buffer)? {
+            0 => Ok(None),
+            _ => Ok(Some(buffer[0])),
+        }
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        if bytes.len() > self.page_size {
+            return self.source.get_by_bytes(position, bytes);
+        }
+        let mut readed = 0;
+        while readed < bytes.len() {
+            let count = self.read_page(position + readed as u64, &mut bytes[readed..])?;
+            if count == 0 {
+                break;
+            }
+            readed += count;
+        }
+        Ok(readed)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        self.source.length()
+    }
+}
+
+#[test]
+fn cached_conformance_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+    use crate::example::random_access_source::{check_conformance, conformance_bytes};
+
+    let bytes = conformance_bytes();
+    check_conformance(&CachedRandomAccessSource::new(ArrayRandomAccessSource::new(bytes.clone())), &bytes);
+    let tiny = CachedRandomAccessSource::with_page_size(ArrayRandomAccessSource::new(bytes.clone()), 100, 2).unwrap();
+    check_conformance(&tiny, &bytes);
+}
+
+#[test]
+fn cached_lru_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let bytes: Vec<u8> = (0..=255).collect();
+    let cached = CachedRandomAccessSource::with_page_size(ArrayRandomAccessSource::new(bytes), 16, 2).unwrap();
+    for position in 0..16 {
+        assert_eq!(cached.get(position).unwrap(), Some(position as u8));
+    }
+    assert_eq!((cached.hits(), cached.misses()), (15, 1));
+
+    // page 0 is used after page 1, so reading page 2 evicts page 1
+    cached.get(16).unwrap();
+    cached.get(0).unwrap();
+    cached.get(32).unwrap();
+    cached.reset_stats();
+    cached.get(1).unwrap();
+    assert_eq!((cached.hits(), cached.misses()), (1, 0));
+    cached.get(17).unwrap();
+    assert_eq!((cached.hits(), cached.misses()), (1, 1));
+
+    let mut buffer = [0u8; 12];
+    assert_eq!(cached.get_by_bytes(250, &mut buffer).unwrap(), 6);
+    assert_eq!(buffer[5], 255);
+
+    // a read longer than a page neither uses nor fills the cache
+    cached.reset_stats();
+    let mut buffer = [0u8; 40];
+    assert_eq!(cached.get_by_bytes(230, &mut buffer).unwrap(), 26);
+    assert_eq!(buffer[25], 255);
+    assert_eq!((cached.hits(), cached.misses()), (0, 0));
+    assert!(CachedRandomAccessSource::with_page_size(ArrayRandomAccessSource::new(vec![]), 0, 1).is_err());
+}
\ No newline at end of file
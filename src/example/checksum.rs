@@ -0,0 +1,620 @@
+use std::fmt;
+use std::io;
+use std::io::ErrorKind;
+use std::ops::Range;
+use std::sync::mpsc;
+use std::thread;
+
+use crate::example::parallel::map_parallel;
+use crate::example::random_access_source::RandomAccessSource;
+
+/// every streaming checksum reads through one buffer of this size
+const BUFFER_SIZE: usize = 64 * 1024;
+/// the part of a range one thread of `checksum_parallel` works on at a time
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const ADLER_MODULUS: u32 = 65521;
+/// most bytes Adler-32 can sum before its u32 sums could overflow
+const ADLER_NMAX: usize = 5552;
+
+const XXH_PRIME64_1: u64 = 0x9E37_79B1_85EB_CA87;
+const XXH_PRIME64_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const XXH_PRIME64_3: u64 = 0x1656_67B1_9E37_79F9;
+const XXH_PRIME64_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const XXH_PRIME64_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+const SHA256_INITIAL: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+const SHA256_ROUNDS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Crc32,
+    Adler32,
+    XxHash64,
+    Sha256,
+}
+
+impl Algorithm {
+    pub fn digest(self) -> Box<dyn Digest + Send> {
+        match self {
+            Algorithm::Crc32 => Box::new(Crc32::new()),
+            Algorithm::Adler32 => Box::new(Adler32::new()),
+            Algorithm::XxHash64 => Box::new(XxHash64::new(0)),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+        }
+    }
+
+    /// whether the checksums of two adjacent parts give the checksum of both, so parts can be summed apart
+    pub fn is_combinable(self) -> bool {
+        matches!(self, Algorithm::Crc32 | Algorithm::Adler32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Checksum {
+    Crc32(u32),
+    Adler32(u32),
+    XxHash64(u64),
+    Sha256([u8; 32]),
+}
+
+impl Checksum {
+    /// the checksum of a part followed by one of length bytes whose checksum is next, None unless both use the same
+    /// combinable algorithm
+    pub fn combine(self, next: Checksum, length: u64) -> Option<Checksum> {
+        match (self, next) {
+            (Checksum::Crc32(first), Checksum::Crc32(second)) => Some(Checksum::Crc32(crc32_combine(first, second, length))),
+            (Checksum::Adler32(first), Checksum::Adler32(second)) =>
+                Some(Checksum::Adler32(adler32_combine(first, second, length))),
+            _ => None,
+        }
+    }
+}
+
+/// lowercase hex, as the usual command line tools print it
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Checksum::Crc32(value) | Checksum::Adler32(value) => write!(f, "{:08x}", value),
+            Checksum::XxHash64(value) => write!(f, "{:016x}", value),
+            Checksum::Sha256(bytes) => bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
+        }
+    }
+}
+
+/// A checksum computed over bytes fed to it in any number of pieces.
+pub trait Digest {
+    fn update(&mut self, bytes: &[u8]);
+
+    /// the checksum of everything fed so far, more may still be fed afterwards
+    fn finish(&self) -> Checksum;
+}
+
+/// CRC-32 as zlib, ZIP and PNG use it
+#[derive(Debug, Clone, Default)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        Crc32 { crc: 0 }
+    }
+}
+
+impl Digest for Crc32 {
+    fn update(&mut self, bytes: &[u8]) {
+        let mut crc = !self.crc;
+        for byte in bytes {
+            crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        self.crc = !crc;
+    }
+
+    fn finish(&self) -> Checksum {
+        Checksum::Crc32(self.crc)
+    }
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32_POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+/// a times b modulo the CRC-32 polynomial, both in the reflected bit order of the checksum
+fn crc32_multiply(a: u32, mut b: u32) -> u32 {
+    let mut product = 0;
+    let mut bit = 1u32 << 31;
+    while bit != 0 {
+        if a & bit != 0 {
+            product ^= b;
+        }
+        b = if b & 1 == 1 { (b >> 1) ^ CRC32_POLYNOMIAL } else { b >> 1 };
+        bit >>= 1;
+    }
+    product
+}
+
+/// the CRC-32 of two parts from the CRC-32 of each, shifting the first over the length of the second
+fn crc32_combine(first: u32, second: u32, second_length: u64) -> u32 {
+    // x^(8 * length) by squaring, x^0 being the top bit in reflected order
+    let mut shift = 1u32 << 31;
+    let mut square = 1u32 << 23;
+    let mut length = second_length;
+    while length != 0 {
+        if length & 1 == 1 {
+            shift = crc32_multiply(square, shift);
+        }
+        square = crc32_multiply(square, square);
+        length >>= 1;
+    }
+    crc32_multiply(shift, first) ^ second
+}
+
+#[derive(Debug, Clone)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+}
+
+impl Default for Adler32 {
+    fn default() -> Adler32 {
+        Adler32::new()
+    }
+}
+
+impl Digest for Adler32 {
+    fn update(&mut self, bytes: &[u8]) {
+        // reduce only once per block, the sums cannot overflow before
+        for block in bytes.chunks(ADLER_NMAX) {
+            for byte in block {
+                self.a += *byte as u32;
+                self.b += self.a;
+            }
+            self.a %= ADLER_MODULUS;
+            self.b %= ADLER_MODULUS;
+        }
+    }
+
+    fn finish(&self) -> Checksum {
+        Checksum::Adler32((self.b << 16) | self.a)
+    }
+}
+
+fn adler32_combine(first: u32, second: u32, second_length: u64) -> u32 {
+    let modulus = ADLER_MODULUS as u64;
+    let remainder = second_length % modulus;
+    let (first_a, first_b) = ((first & 0xFFFF) as u64, (first >> 16) as u64);
+    let (second_a, second_b) = ((second & 0xFFFF) as u64, (second >> 16) as u64);
+    // every byte of the second part added first_a - 1 more to b than it did on its own
+    let a = (first_a + second_a + modulus - 1) % modulus;
+    let b = (remainder * first_a + first_b + second_b + modulus - remainder) % modulus;
+    ((b << 16) | a) as u32
+}
+
+/// XXH64, the 64 bit xxHash
+#[derive(Debug, Clone)]
+pub struct XxHash64 {
+    seed: u64,
+    lanes: [u64; 4],
+    /// bytes of an unfinished stripe
+    pending: [u8; 32],
+    pending_length: usize,
+    total: u64,
+}
+
+impl XxHash64 {
+    pub fn new(seed: u64) -> XxHash64 {
+        XxHash64 {
+            seed,
+            lanes: [
+                seed.wrapping_add(XXH_PRIME64_1).wrapping_add(XXH_PRIME64_2),
+                seed.wrapping_add(XXH_PRIME64_2),
+                seed,
+                seed.wrapping_sub(XXH_PRIME64_1),
+            ],
+            pending: [0; 32],
+            pending_length: 0,
+            total: 0,
+        }
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (lane, word) in self.lanes.iter_mut().zip(stripe.chunks_exact(8)) {
+            *lane = xxh64_round(*lane, u64::from_le_bytes(word.try_into().unwrap()));
+        }
+    }
+}
+
+fn xxh64_round(accumulator: u64, input: u64) -> u64 {
+    accumulator.wrapping_add(input.wrapping_mul(XXH_PRIME64_2)).rotate_left(31).wrapping_mul(XXH_PRIME64_1)
+}
+
+fn xxh64_merge(hash: u64, lane: u64) -> u64 {
+    (hash ^ xxh64_round(0, lane)).wrapping_mul(XXH_PRIME64_1).wrapping_add(XXH_PRIME64_4)
+}
+
+impl Digest for XxHash64 {
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+        if self.pending_length > 0 {
+            let taken = bytes.len().min(32 - self.pending_length);
+            self.pending[self.pending_length..self.pending_length + taken].copy_from_slice(&bytes[..taken]);
+            self.pending_length += taken;
+            bytes = &bytes[taken..];
+            if self.pending_length < 32 {
+                return;
+            }
+            let stripe = self.pending;
+            self.stripe(&stripe);
+            self.pending_length = 0;
+        }
+        let mut stripes = bytes.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_length = rest.len();
+    }
+
+    fn finish(&self) -> Checksum {
+        let mut hash = match self.total {
+            0..=31 => self.seed.wrapping_add(XXH_PRIME64_5),
+            _ => {
+                let [first, second, third, fourth] = self.lanes;
+                let hash = first.rotate_left(1).wrapping_add(second.rotate_left(7))
+                    .wrapping_add(third.rotate_left(12)).wrapping_add(fourth.rotate_left(18));
+                self.lanes.iter().fold(hash, |hash, lane| xxh64_merge(hash, *lane))
+            }
+        };
+        hash = hash.wrapping_add(self.total);
+
+        let mut rest = &self.pending[..self.pending_length];
+        while rest.len() >= 8 {
+            let word = u64::from_le_bytes(rest[..8].try_into().unwrap());
+            hash = (hash ^ xxh64_round(0, word)).rotate_left(27).wrapping_mul(XXH_PRIME64_1).wrapping_add(XXH_PRIME64_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let word = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+            hash = (hash ^ word.wrapping_mul(XXH_PRIME64_1)).rotate_left(23).wrapping_mul(XXH_PRIME64_2)
+                .wrapping_add(XXH_PRIME64_3);
+            rest = &rest[4..];
+        }
+        for byte in rest {
+            hash = (hash ^ (*byte as u64).wrapping_mul(XXH_PRIME64_5)).rotate_left(11).wrapping_mul(XXH_PRIME64_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(XXH_PRIME64_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(XXH_PRIME64_3);
+        hash ^= hash >> 32;
+        Checksum::XxHash64(hash)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    pending: [u8; 64],
+    pending_length: usize,
+    total: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Sha256 {
+        Sha256 { state: SHA256_INITIAL, pending: [0; 64], pending_length: 0, total: 0 }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Sha256 {
+        Sha256::new()
+    }
+}
+
+fn sha256_block(state: &mut [u32; 8], block: &[u8]) {
+    let mut schedule = [0u32; 64];
+    for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    for index in 16..64 {
+        let (before15, before2) = (schedule[index - 15], schedule[index - 2]);
+        let s0 = before15.rotate_right(7) ^ before15.rotate_right(18) ^ (before15 >> 3);
+        let s1 = before2.rotate_right(17) ^ before2.rotate_right(19) ^ (before2 >> 10);
+        schedule[index] = schedule[index - 16].wrapping_add(s0).wrapping_add(schedule[index - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (round, word) in SHA256_ROUNDS.iter().zip(schedule) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(*round).wrapping_add(word);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *value = value.wrapping_add(add);
+    }
+}
+
+impl Digest for Sha256 {
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+        if self.pending_length > 0 {
+            let taken = bytes.len().min(64 - self.pending_length);
+            self.pending[self.pending_length..self.pending_length + taken].copy_from_slice(&bytes[..taken]);
+            self.pending_length += taken;
+            bytes = &bytes[taken..];
+            if self.pending_length < 64 {
+                return;
+            }
+            sha256_block(&mut self.state, &self.pending);
+            self.pending_length = 0;
+        }
+        let mut blocks = bytes.chunks_exact(64);
+        for block in &mut blocks {
+            sha256_block(&mut self.state, block);
+        }
+        let rest = blocks.remainder();
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_length = rest.len();
+    }
+
+    fn finish(&self) -> Checksum {
+        // pad a copy, so more can still be fed to self
+        let mut state = self.state;
+        let mut last = [0u8; 128];
+        last[..self.pending_length].copy_from_slice(&self.pending[..self.pending_length]);
+        last[self.pending_length] = 0x80;
+        let length = if self.pending_length < 56 { 64 } else { 128 };
+        last[length - 8..length].copy_from_slice(&(self.total * 8).to_be_bytes());
+        for block in last[..length].chunks_exact(64) {
+            sha256_block(&mut state, block);
+        }
+        let mut digest = [0u8; 32];
+        for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+            bytes.copy_from_slice(&value.to_be_bytes());
+        }
+        Checksum::Sha256(digest)
+    }
+}
+
+fn check_range<S: RandomAccessSource + ?Sized>(source: &S, range: &Range<u64>) -> io::Result<()> {
+    if range.start > range.end || range.end > source.length()? {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "range is outside of the source"));
+    }
+    Ok(())
+}
+
+/// the checksum of range of source, read through one fixed buffer
+pub fn checksum<S: RandomAccessSource + ?Sized>(source: &S, range: Range<u64>, algorithm: Algorithm)
+                                                -> io::Result<Checksum> {
+    check_range(source, &range)?;
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut digest = algorithm.digest();
+    feed(source, range, digest.as_mut(), &mut buffer)?;
+    Ok(digest.finish())
+}
+
+fn feed<S: RandomAccessSource + ?Sized>(source: &S, range: Range<u64>, digest: &mut dyn Digest, buffer: &mut [u8])
+                                        -> io::Result<()> {
+    let mut position = range.start;
+    while position < range.end {
+        let length = ((range.end - position).min(buffer.len() as u64)) as usize;
+        source.read_fully_at(position, &mut buffer[..length])?;
+        digest.update(&buffer[..length]);
+        position += length as u64;
+    }
+    Ok(())
+}
+
+/// the checksum of range of source, split across one thread per cpu
+pub fn checksum_parallel<S: RandomAccessSource + Sync + ?Sized>(source: &S, range: Range<u64>, algorithm: Algorithm)
+                                                                -> io::Result<Checksum> {
+    checksum_parallel_with(source, range, algorithm, CHUNK_SIZE, num_cpus::get())
+}
+
+/// the checksum of range of source, read in chunks of chunk_size by up to workers threads.
+/// CRC-32 and Adler-32 sum every chunk on its own thread and combine the results. xxHash64 and SHA-256 have no
+/// combination, so only the reading is spread: every thread reads its share of the chunks ahead, at most one
+/// waiting at a time, while they are hashed in order. the result is always the one `checksum` gives.
+pub fn checksum_parallel_with<S: RandomAccessSource + Sync + ?Sized>(source: &S, range: Range<u64>,
+                                                                     algorithm: Algorithm, chunk_size: u64,
+                                                                     workers: usize) -> io::Result<Checksum> {
+    check_range(source, &range)?;
+    let chunks = split(&range, chunk_size);
+    let workers = workers.min(chunks.len()).max(1);
+    if workers == 1 {
+        return checksum(source, range, algorithm);
+    }
+    if algorithm.is_combinable() {
+        let sums = map_parallel(&chunks, workers, BUFFER_SIZE, |chunk, buffer| {
+            let mut digest = algorithm.digest();
+            feed(source, chunk.clone(), digest.as_mut(), buffer)?;
+            Ok(digest.finish())
+        }).into_iter().collect::<io::Result<Vec<_>>>()?;
+        let first = algorithm.digest().finish();
+        return Ok(chunks.iter().zip(sums).fold(first, |sum, (chunk, next)| {
+            sum.combine(next, chunk.end - chunk.start).expect("chunks summed with one combinable algorithm")
+        }));
+    }
+
+    // every chunk read ahead is held in memory until it is hashed, so bound them whatever chunk_size is
+    let chunks = split(&range, chunk_size.min(CHUNK_SIZE));
+    let mut digest = algorithm.digest();
+    thread::scope(|scope| -> io::Result<()> {
+        // worker n reads chunks n, n + workers, ... into its own channel, so taking from the channels in turn
+        // gives the chunks in order
+        let receivers: Vec<mpsc::Receiver<io::Result<Vec<u8>>>> = (0..workers).map(|worker| {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            let chunks = &chunks;
+            scope.spawn(move || {
+                for chunk in chunks.iter().skip(worker).step_by(workers) {
+                    let mut data = vec![0u8; (chunk.end - chunk.start) as usize];
+                    let read = source.read_fully_at(chunk.start, &mut data).map(|_| data);
+                    let failed = read.is_err();
+                    // a closed channel means hashing stopped on an error
+                    if sender.send(read).is_err() || failed {
+                        return;
+                    }
+                }
+            });
+            receiver
+        }).collect();
+        for index in 0..chunks.len() {
+            let data = receivers[index % workers].recv().expect("a worker sends every chunk it reads")?;
+            digest.update(&data);
+        }
+        Ok(())
+    })?;
+    Ok(digest.finish())
+}
+
+/// range cut into pieces of chunk_size, the last one shorter
+fn split(range: &Range<u64>, chunk_size: u64) -> Vec<Range<u64>> {
+    let chunk_size = chunk_size.max(1);
+    let mut chunks = Vec::new();
+    let mut start = range.start;
+    while start < range.end {
+        let end = start.saturating_add(chunk_size).min(range.end);
+        chunks.push(start..end);
+        start = end;
+    }
+    chunks
+}
+
+#[test]
+fn checksum_vectors_test() {
+    let digest = |algorithm: Algorithm, bytes: &[u8]| {
+        let mut digest = algorithm.digest();
+        digest.update(bytes);
+        digest.finish().to_string()
+    };
+    assert_eq!(digest(Algorithm::Crc32, b"123456789"), "cbf43926");
+    assert_eq!(digest(Algorithm::Crc32, b""), "00000000");
+    assert_eq!(digest(Algorithm::Adler32, b"Wikipedia"), "11e60398");
+    assert_eq!(digest(Algorithm::Adler32, b""), "00000001");
+    assert_eq!(digest(Algorithm::XxHash64, b""), "ef46db3751d8e999");
+    assert_eq!(digest(Algorithm::XxHash64, b"a"), "d24ec4f1a98c6e5b");
+    assert_eq!(digest(Algorithm::XxHash64, b"abc"), "44bc2cf5ad770999");
+    assert_eq!(digest(Algorithm::XxHash64, b"Nobody inspects the spammish repetition"), "fbcea83c8a378bf1");
+    assert_eq!(digest(Algorithm::Sha256, b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(digest(Algorithm::Sha256, b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+    // fed in uneven pieces, crossing every block and stripe boundary
+    let bytes: Vec<u8> = (0..100_000u32).map(|index| ((index * 7 + 3) % 251) as u8).collect();
+    for algorithm in [Algorithm::Crc32, Algorithm::Adler32, Algorithm::XxHash64, Algorithm::Sha256] {
+        let mut pieces = algorithm.digest();
+        let mut start = 0;
+        for length in (1..).map(|step| step * 13 % 97) {
+            let end = (start + length).min(bytes.len());
+            pieces.update(&bytes[start..end]);
+            start = end;
+            if start == bytes.len() {
+                break;
+            }
+        }
+        assert_eq!(pieces.finish().to_string(), digest(algorithm, &bytes), "{:?}", algorithm);
+    }
+    assert_eq!(digest(Algorithm::Crc32, &bytes), "43bfeeb4");
+    assert_eq!(digest(Algorithm::Adler32, &bytes), "8037c560");
+    assert_eq!(digest(Algorithm::Sha256, &bytes), "5889ab642baa09c41570b8888cbf45f3762152cea2490ea6b150208a99c92b10");
+
+    assert_eq!(Checksum::Crc32(1).combine(Checksum::Adler32(1), 1), None);
+    assert_eq!(Checksum::Sha256([0; 32]).combine(Checksum::Sha256([0; 32]), 1), None);
+}
+
+#[test]
+fn checksum_source_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let bytes: Vec<u8> = (0..300_000u32).map(|index| (index % 253) as u8 ^ (index >> 9) as u8).collect();
+    let source = ArrayRandomAccessSource::new(bytes.clone());
+    for algorithm in [Algorithm::Crc32, Algorithm::Adler32, Algorithm::XxHash64, Algorithm::Sha256] {
+        for range in [0..bytes.len() as u64, 1000..250_001, 7..7] {
+            let mut digest = algorithm.digest();
+            digest.update(&bytes[range.start as usize..range.end as usize]);
+            let expected = digest.finish();
+            assert_eq!(checksum(&source, range.clone(), algorithm).unwrap(), expected);
+            assert_eq!(checksum_parallel(&source, range.clone(), algorithm).unwrap(), expected);
+            // chunks smaller than the buffer and not dividing the range
+            assert_eq!(checksum_parallel_with(&source, range.clone(), algorithm, 9999, 4).unwrap(), expected,
+                       "{:?} {:?}", algorithm, range);
+        }
+    }
+    assert_eq!(checksum(&source, 0..300_001, Algorithm::Crc32).unwrap_err().kind(), ErrorKind::InvalidInput);
+    // a read failing partway stops the hashing and the workers reading ahead of it
+    struct Failing(ArrayRandomAccessSource<Vec<u8>>);
+    impl RandomAccessSource for Failing {
+        fn get(&self, position: u64) -> io::Result<Option<u8>> {
+            self.0.get(position)
+        }
+
+        fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+            match position {
+                100_000..=199_999 => Err(io::Error::other("bad sector")),
+                _ => self.0.get_by_bytes(position, bytes),
+            }
+        }
+
+        fn length(&self) -> io::Result<u64> {
+            self.0.length()
+        }
+    }
+    let failing = Failing(ArrayRandomAccessSource::new(bytes.clone()));
+    for algorithm in [Algorithm::Crc32, Algorithm::Sha256] {
+        let error = checksum_parallel_with(&failing, 0..bytes.len() as u64, algorithm, 9999, 4).unwrap_err();
+        assert_eq!(error.to_string(), "bad sector");
+    }
+
+    let reversed = Range { start: 10, end: 5 };
+    assert!(checksum_parallel(&source, reversed, Algorithm::Sha256).is_err());
+    // a chunk size near the largest range neither overflows nor allocates a chunk that large
+    for algorithm in [Algorithm::Crc32, Algorithm::Sha256] {
+        assert_eq!(checksum_parallel_with(&source, 1..300_000, algorithm, u64::MAX - 1, 4).unwrap(),
+                   checksum(&source, 1..300_000, algorithm).unwrap());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,134 @@
+use std::io;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+/// Several sources one after the other, seen as one contiguous source.
+///
+/// The source holding a position is found by a binary search over the offsets the sources start at, and reads
+/// crossing from one source into the next are split between them.
+pub struct GroupedRandomAccessSource {
+    sources: Vec<Box<dyn RandomAccessSource>>,
+    starts: Vec<u64>,
+    length: u64,
+}
+
+impl GroupedRandomAccessSource {
+    pub fn new(sources: Vec<Box<dyn RandomAccessSource>>) -> io::Result<GroupedRandomAccessSource> {
+        let mut starts = Vec::with_capacity(sources.len());
+        let mut length = 0;
+        for source in &sources {
+            starts.push(length);
+            length += source.length()?;
+        }
+        Ok(GroupedRandomAccessSource { sources, starts, length })
+    }
+
+    /// index of the source holding position, which must be below the length
+    fn source_index(&self, position: u64) -> usize {
+        // empty sources share their start with the next one, so take the last source starting at or before position
+        self.starts.partition_point(|&start| start <= position) - 1
+    }
+}
+
+impl RandomAccessSource for GroupedRandomAccessSource {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        if position >= self.length {
+            return Ok(None);
+        }
+        let index = self.source_index(position);
+        self.sources[index].get(position - self.starts[index])
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        if position >= self.length {
+            return Ok(0);
+        }
+        let mut index = self.source_index(position);
+        let mut readed = 0;
+        while readed < bytes.len() && index < self.sources.len() {
+            let end = self.starts.get(index + 1).copied().unwrap_or(self.length);
+            let local = position + readed as u64 - self.starts[index];
+            // never read past the length the source had when grouped, or its bytes would shift everything after it
+            let share = (end - self.starts[index] - local).min((bytes.len() - readed) as u64) as usize;
+            let mut filled = 0;
+            while filled < share {
+                let count = self.sources[index].get_by_bytes(local + filled as u64, &mut bytes[readed + filled..readed + share])?;
+                if count == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "grouped source is shorter than when it was added"));
+                }
+                filled += count;
+            }
+            readed += share;
+            index += 1;
+        }
+        Ok(readed)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.length)
+    }
+}
+
+#[test]
+fn grouped_conformance_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+    use crate::example::random_access_source::{check_conformance, conformance_bytes, test_file, FileRandomAccessSouce};
+
+    let bytes = conformance_bytes();
+    let path = test_file("grouped", &bytes[..6000]);
+    let grouped = GroupedRandomAccessSource::new(vec![
+        Box::new(FileRandomAccessSouce::new(&path).unwrap()),
+        Box::new(ArrayRandomAccessSource::new(Vec::new())),
+        Box::new(ArrayRandomAccessSource::new(bytes[6000..6001].to_vec())),
+        Box::new(ArrayRandomAccessSource::new(bytes[6001..].to_vec())),
+        Box::new(ArrayRandomAccessSource::new(Vec::new())),
+    ]).unwrap();
+    check_conformance(&grouped, &bytes);
+    check_conformance(&GroupedRandomAccessSource::new(Vec::new()).unwrap(), &[]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn grouped_overlay_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    // an incremental update appended to the original bytes without touching them
+    let original: &'static [u8] = b"%PDF-1.7 original";
+    let update = b" update".to_vec();
+    let document = GroupedRandomAccessSource::new(vec![
+        Box::new(ArrayRandomAccessSource::new(original)),
+        Box::new(ArrayRandomAccessSource::new(update)),
+    ]).unwrap();
+    assert_eq!(document.length().unwrap(), 24);
+    assert_eq!(document.get(17).unwrap(), Some(b' '));
+    let mut bytes = [0u8; 10];
+    assert_eq!(document.get_by_bytes(12, &mut bytes).unwrap(), 10);
+    assert_eq!(&bytes, b"ginal upda");
+    assert_eq!(document.get(24).unwrap(), None);
+}
+
+#[test]
+fn grouped_changed_source_test() {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+    use crate::example::random_access_source::{test_file, FileRandomAccessSouce};
+
+    let path = test_file("grouped_changed", b"0123");
+    let grouped = GroupedRandomAccessSource::new(vec![
+        Box::new(FileRandomAccessSouce::new(&path).unwrap()),
+        Box::new(ArrayRandomAccessSource::new(b"abcd".to_vec())),
+    ]).unwrap();
+
+    // bytes appended to the first source stay hidden behind the length it had
+    OpenOptions::new().append(true).open(&path).unwrap().write_all(b"45").unwrap();
+    let mut bytes = [0u8; 6];
+    assert_eq!(grouped.get_by_bytes(1, &mut bytes).unwrap(), 6);
+    assert_eq!(&bytes, b"123abc");
+
+    // and a source that shrank is an error rather than a silent shift
+    std::fs::write(&path, b"01").unwrap();
+    let error = grouped.get_by_bytes(1, &mut bytes).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,527 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+const DEFAULT_READ_AHEAD: usize = 64 * 1024;
+/// number of fetched blocks kept for later reads
+const BLOCK_CAPACITY: usize = 16;
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// longest status or header line accepted from the server
+const MAX_LINE_LENGTH: usize = 8192;
+
+struct State {
+    /// the kept-alive connection, opened again when the server closed it
+    connection: Option<BufReader<TcpStream>>,
+    /// fetched blocks by their start, the most recently used last
+    blocks: VecDeque<(u64, Vec<u8>)>,
+}
+
+/// A `RandomAccessSource` over a file served by an HTTP/1.1 server, read with `Range` requests.
+///
+/// Every miss fetches read_ahead bytes more than asked for and keeps the block, so small sequential reads cost one
+/// request per block. `read_ranges` coalesces ranges that touch into one request. Only plain `http://` URLs are
+/// supported, through one kept-alive connection.
+pub struct HttpRandomAccessSource {
+    address: String,
+    path: String,
+    length: u64,
+    read_ahead: usize,
+    state: Mutex<State>,
+    requests: AtomicU64,
+}
+
+/// what a response to a range request held
+struct Fetched {
+    start: u64,
+    bytes: Vec<u8>,
+    /// length of the whole file
+    total: u64,
+}
+
+impl HttpRandomAccessSource {
+    pub fn new(url: &str) -> io::Result<HttpRandomAccessSource> {
+        HttpRandomAccessSource::with_read_ahead(url, DEFAULT_READ_AHEAD)
+    }
+
+    /// fetch read_ahead bytes past every read that misses. opening costs one request, which already fetches the
+    /// first block
+    pub fn with_read_ahead(url: &str, read_ahead: usize) -> io::Result<HttpRandomAccessSource> {
+        let rest = url.strip_prefix("http://")
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "only http:// URLs are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "URL has no host"));
+        }
+        let address = with_port(authority);
+        let mut source = HttpRandomAccessSource {
+            address,
+            path: path.to_string(),
+            length: 0,
+            read_ahead: read_ahead.max(1),
+            state: Mutex::new(State { connection: None, blocks: VecDeque::new() }),
+            requests: AtomicU64::new(0),
+        };
+
+        let state = source.state.get_mut().unwrap();
+        let fetched = fetch(&source.address, &source.path, &source.requests, state, 0..source.read_ahead as u64)?;
+        source.length = fetched.total;
+        if !fetched.bytes.is_empty() {
+            state.blocks.push_back((fetched.start, fetched.bytes));
+        }
+        Ok(source)
+    }
+
+    /// number of HTTP requests sent so far
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// the bytes of every range, in the order given. ranges that touch or lie at most gap bytes apart are read with
+    /// one request; a range past the end fails with `UnexpectedEof`
+    pub fn read_ranges(&self, ranges: &[Range<u64>], gap: u64) -> io::Result<Vec<Vec<u8>>> {
+        // checked before anything is read, so a range far past the end fails instead of allocating its length
+        for range in ranges {
+            if range.start > range.end {
+                return Err(io::Error::new(ErrorKind::InvalidInput, "range ends before it starts"));
+            }
+            if range.end > self.length {
+                return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("range ends past {}", self.length)));
+            }
+        }
+        let mut order: Vec<usize> = (0..ranges.len()).collect();
+        order.sort_by_key(|index| ranges[*index].start);
+
+        let mut results = vec![Vec::new(); ranges.len()];
+        let mut group: Vec<usize> = Vec::new();
+        let mut group_range: Range<u64> = 0..0;
+        for index in order {
+            let range = &ranges[index];
+            if !group.is_empty() && range.start > group_range.end.saturating_add(gap) {
+                self.read_group(ranges, &group, group_range.clone(), &mut results)?;
+                group.clear();
+            }
+            if group.is_empty() {
+                group_range = range.clone();
+            }
+            group_range.end = group_range.end.max(range.end);
+            group.push(index);
+        }
+        if !group.is_empty() {
+            self.read_group(ranges, &group, group_range, &mut results)?;
+        }
+        Ok(results)
+    }
+
+    fn read_group(&self, ranges: &[Range<u64>], group: &[usize], range: Range<u64>, results: &mut [Vec<u8>])
+                  -> io::Result<()> {
+        let mut bytes = vec![0u8; (range.end - range.start) as usize];
+        self.read_fully_at(range.start, &mut bytes)?;
+        for index in group {
+            let part = &ranges[*index];
+            results[*index] = bytes[(part.start - range.start) as usize..(part.end - range.start) as usize].to_vec();
+        }
+        Ok(())
+    }
+}
+
+/// authority with the default port added when it has none, an IPv6 address being the bracketed `[::1]` form
+fn with_port(authority: &str) -> String {
+    let host_end = authority.rfind(']').map_or(0, |bracket| bracket + 1);
+    match authority[host_end..].contains(':') {
+        true => authority.to_string(),
+        false => format!("{}:80", authority),
+    }
+}
+
+/// copy the bytes at position out of a kept block, marking it used
+fn copy_cached(blocks: &mut VecDeque<(u64, Vec<u8>)>, position: u64, bytes: &mut [u8]) -> usize {
+    let found = blocks.iter().position(|(start, block)| *start <= position && position < start + block.len() as u64);
+    let Some(index) = found else {
+        return 0;
+    };
+    let block = blocks.remove(index).unwrap();
+    let offset = (position - block.0) as usize;
+    let count = bytes.len().min(block.1.len() - offset);
+    bytes[..count].copy_from_slice(&block.1[offset..offset + count]);
+    blocks.push_back(block);
+    count
+}
+
+/// send one range request for range, on the kept-alive connection when there is one.
+/// a free function so that opening can call it before the source exists
+fn fetch(address: &str, path: &str, requests: &AtomicU64, state: &mut State, range: Range<u64>)
+                -> io::Result<Fetched> {
+    // a kept-alive connection may have been closed by the server in between, which only shows once it is used
+    if let Some(connection) = state.connection.take() {
+        requests.fetch_add(1, Ordering::Relaxed);
+        match request(connection, address, path, &range) {
+            Ok((fetched, connection)) => {
+                state.connection = connection;
+                return Ok(fetched);
+            }
+            Err(error) if !matches!(error.kind(), ErrorKind::UnexpectedEof | ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted) => return Err(error),
+            Err(_) => {}
+        }
+    }
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    requests.fetch_add(1, Ordering::Relaxed);
+    let (fetched, connection) = request(BufReader::new(stream), address, path, &range)?;
+    state.connection = connection;
+    Ok(fetched)
+}
+
+/// the response to a request for range, with the connection when the server keeps it open
+fn request(mut connection: BufReader<TcpStream>, host: &str, path: &str, range: &Range<u64>)
+           -> io::Result<(Fetched, Option<BufReader<TcpStream>>)> {
+    write!(connection.get_mut(), "GET {} HTTP/1.1\r\nHost: {}\r\nRange: bytes={}-{}\r\n\r\n",
+           path, host, range.start, range.end - 1)?;
+    connection.get_mut().flush()?;
+
+    let status_line = read_line(&mut connection)?;
+    let status = status_line.split(' ').nth(1).and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| invalid(format!("bad status line {:?}", status_line)))?;
+    let mut content_length = None;
+    let mut content_range = None;
+    let mut keep_alive = status_line.starts_with("HTTP/1.1");
+    loop {
+        let line = read_line(&mut connection)?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(invalid(format!("bad header {:?}", line)));
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = Some(value.parse::<u64>().map_err(|_| invalid("bad Content-Length"))?),
+            "content-range" => content_range = Some(value.to_string()),
+            "connection" => keep_alive = !value.eq_ignore_ascii_case("close"),
+            "transfer-encoding" if !value.eq_ignore_ascii_case("identity") =>
+                return Err(invalid(format!("unsupported Transfer-Encoding {}", value))),
+            _ => {}
+        }
+    }
+
+    // a server ignoring the range sends the whole file, of which only what is needed to reach the end of the range
+    // is read, dropping the connection instead of draining the rest
+    let wanted = match (status, content_length) {
+        (200, Some(length)) => Some(length.min(range.end)),
+        _ => content_length,
+    };
+    // without a length the body runs to the end of the connection
+    let mut body = Vec::new();
+    match wanted {
+        Some(length) => {
+            (&mut connection).take(length).read_to_end(&mut body)?;
+            if (body.len() as u64) < length {
+                return Err(io::Error::new(ErrorKind::UnexpectedEof, "connection closed inside the body"));
+            }
+            keep_alive &= wanted == content_length;
+        }
+        None => {
+            connection.read_to_end(&mut body)?;
+            keep_alive = false;
+        }
+    }
+    let connection = if keep_alive { Some(connection) } else { None };
+
+    let fetched = match status {
+        // the server sent the file from its start, keep only the range so blocks stay read_ahead sized
+        200 => {
+            let total = content_length.unwrap_or(body.len() as u64);
+            let start = range.start.min(body.len() as u64);
+            let bytes = body.split_off(start as usize);
+            Fetched { start, bytes, total }
+        }
+        206 => {
+            let (start, total) = content_range.as_deref().and_then(parse_content_range)
+                .ok_or_else(|| invalid("206 response without a valid Content-Range"))?;
+            Fetched { start, bytes: body, total }
+        }
+        // asked past the end, which for an empty file is any range
+        416 => {
+            let total = content_range.as_deref().and_then(|value| value.strip_prefix("bytes */"))
+                .and_then(|total| total.parse().ok())
+                .ok_or_else(|| invalid("416 response without the length of the file"))?;
+            Fetched { start: total, bytes: Vec::new(), total }
+        }
+        other => return Err(io::Error::other(format!("HTTP status {}", other))),
+    };
+    Ok((fetched, connection))
+}
+
+/// start and total length of `bytes start-end/total`
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()?))
+}
+
+fn read_line(connection: &mut BufReader<TcpStream>) -> io::Result<String> {
+    let mut line = Vec::new();
+    connection.by_ref().take(MAX_LINE_LENGTH as u64).read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "response line is cut or too long"));
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("response line is not UTF-8"))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.into())
+}
+
+impl RandomAccessSource for HttpRandomAccessSource {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        let mut buffer: [u8; 1] = [0];
+        match self.get_by_bytes(position, &mut buffer)? {
+            0 => Ok(None),
+            _ => Ok(Some(buffer[0])),
+        }
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        if position >= self.length {
+            return Ok(0);
+        }
+        let wanted = bytes.len().min((self.length - position).min(usize::MAX as u64) as usize);
+        let mut state = self.state.lock().unwrap();
+        let mut readed = 0;
+        while readed < wanted {
+            let current = position + readed as u64;
+            let count = copy_cached(&mut state.blocks, current, &mut bytes[readed..wanted]);
+            if count > 0 {
+                readed += count;
+                continue;
+            }
+
+            let end = (current + (wanted - readed) as u64).saturating_add(self.read_ahead as u64).min(self.length);
+            let fetched = fetch(&self.address, &self.path, &self.requests, &mut state, current..end)?;
+            let fetched_end = fetched.start + fetched.bytes.len() as u64;
+            if fetched.start > current || fetched_end <= current {
+                return Err(invalid(format!("server did not send the bytes at {}", current)));
+            }
+            let offset = (current - fetched.start) as usize;
+            let count = (wanted - readed).min(fetched.bytes.len() - offset);
+            bytes[readed..readed + count].copy_from_slice(&fetched.bytes[offset..offset + count]);
+            readed += count;
+
+            // keep what was read ahead, and the whole block unless it is much bigger than a read-ahead block
+            let keep_from = match fetched.bytes.len() > 4 * self.read_ahead {
+                true => (offset + count).min(fetched.bytes.len().saturating_sub(self.read_ahead)),
+                false => 0,
+            };
+            if keep_from < fetched.bytes.len() {
+                if state.blocks.len() >= BLOCK_CAPACITY {
+                    state.blocks.pop_front();
+                }
+                let block = fetched.bytes[keep_from..].to_vec();
+                state.blocks.push_back((fetched.start + keep_from as u64, block));
+            }
+        }
+        Ok(readed)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.length)
+    }
+}
+
+/// A tiny HTTP/1.1 file server on a local port, answering range requests for one file, for tests only.
+#[cfg(test)]
+pub(crate) struct TestServer {
+    pub address: std::net::SocketAddr,
+    /// the Range header of every request, empty when there was none
+    pub ranges: std::sync::Arc<Mutex<Vec<String>>>,
+    pub connections: std::sync::Arc<AtomicU64>,
+    stop: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[cfg(test)]
+impl TestServer {
+    /// serve bytes, ignoring Range headers when honor_ranges is false
+    pub(crate) fn start(bytes: Vec<u8>, honor_ranges: bool) -> TestServer {
+        use std::net::TcpListener;
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let connections = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let bytes = Arc::new(bytes);
+        let server = TestServer { address, ranges: Arc::clone(&ranges), connections: Arc::clone(&connections),
+                                  stop: Arc::clone(&stop) };
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Ok(stream) = stream else { continue };
+                connections.fetch_add(1, Ordering::Relaxed);
+                let (bytes, ranges) = (Arc::clone(&bytes), Arc::clone(&ranges));
+                thread::spawn(move || {
+                    let _ = TestServer::serve(stream, &bytes, &ranges, honor_ranges);
+                });
+            }
+        });
+        server
+    }
+
+    fn serve(stream: TcpStream, bytes: &[u8], ranges: &Mutex<Vec<String>>, honor_ranges: bool) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut stream = stream;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let path = line.split(' ').nth(1).unwrap_or("").to_string();
+            let mut range = String::new();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header)?;
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some(value) = header.strip_prefix("Range: ") {
+                    range = value.to_string();
+                }
+            }
+            ranges.lock().unwrap().push(range.clone());
+
+            if path != "/file.bin" {
+                write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")?;
+                continue;
+            }
+            let requested = range.strip_prefix("bytes=").and_then(|range| range.split_once('-'))
+                .and_then(|(start, end)| Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?)));
+            match requested {
+                Some((start, _)) if honor_ranges && start >= bytes.len() =>
+                    write!(stream, "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\n\
+Content-Length: 0\r\n\r\n", bytes.len())?,
+                Some((start, end)) if honor_ranges => {
+                    let end = end.min(bytes.len() - 1);
+                    write!(stream, "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n\
+Content-Length: {}\r\n\r\n", start, end, bytes.len(), end + 1 - start)?;
+                    stream.write_all(&bytes[start..=end])?;
+                }
+                _ => {
+                    write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", bytes.len())?;
+                    stream.write_all(bytes)?;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // wake the accepting thread so it sees the flag and ends
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+#[test]
+fn http_conformance_test() {
+    use crate::example::random_access_source::{check_conformance, conformance_bytes};
+
+    let expected = conformance_bytes();
+    let server = TestServer::start(expected.clone(), true);
+    check_conformance(&HttpRandomAccessSource::with_read_ahead(&server.url("/file.bin"), 1000).unwrap(), &expected);
+    check_conformance(&HttpRandomAccessSource::new(&server.url("/file.bin")).unwrap(), &expected);
+    // a server ignoring ranges sends the whole file at once
+    let whole = TestServer::start(expected.clone(), false);
+    let source = HttpRandomAccessSource::with_read_ahead(&whole.url("/file.bin"), 1000).unwrap();
+    check_conformance(&source, &expected);
+    // of which only the asked for block is kept
+    let source = HttpRandomAccessSource::with_read_ahead(&whole.url("/file.bin"), 1000).unwrap();
+    source.get(5000).unwrap();
+    let blocks: Vec<(u64, usize)> = source.state.lock().unwrap().blocks.iter()
+        .map(|(start, block)| (*start, block.len()))
+        .collect();
+    assert_eq!(blocks, vec![(0, 1000), (5000, 1001)]);
+
+    let empty = TestServer::start(Vec::new(), true);
+    let source = HttpRandomAccessSource::new(&empty.url("/file.bin")).unwrap();
+    assert_eq!(source.length().unwrap(), 0);
+    assert_eq!(source.get(0).unwrap(), None);
+}
+
+#[test]
+fn http_read_ahead_test() {
+    let bytes: Vec<u8> = (0..5000u32).map(|index| (index % 199) as u8).collect();
+    let server = TestServer::start(bytes.clone(), true);
+    let source = HttpRandomAccessSource::with_read_ahead(&server.url("/file.bin"), 100).unwrap();
+    assert_eq!((source.length().unwrap(), source.requests()), (5000, 1));
+
+    // small sequential reads cost one request per block
+    let mut read_back = Vec::new();
+    let mut chunk = [0u8; 10];
+    for position in (0..300).step_by(10) {
+        source.read_fully_at(position, &mut chunk).unwrap();
+        read_back.extend_from_slice(&chunk);
+    }
+    assert_eq!(read_back, &bytes[..300]);
+    assert_eq!(source.requests(), 3);
+    assert_eq!(server.ranges.lock().unwrap().clone(), vec!["bytes=0-99", "bytes=100-209", "bytes=210-319"]);
+    // and earlier blocks are still kept
+    assert_eq!(source.get(50).unwrap(), Some(bytes[50]));
+    assert_eq!(source.requests(), 3);
+
+    // touching ranges go out as one request, the others as one each
+    let ranges = [2000..2010, 4000..4004, 2010..2030, 2025..2040];
+    let parts = source.read_ranges(&ranges, 0).unwrap();
+    for (range, part) in ranges.iter().zip(&parts) {
+        assert_eq!(part.as_slice(), &bytes[range.start as usize..range.end as usize]);
+    }
+    assert_eq!(source.requests(), 5);
+    assert_eq!(server.ranges.lock().unwrap()[3..], ["bytes=2000-2139", "bytes=4000-4103"]);
+    // within the gap, far ranges are coalesced too
+    let parts = source.read_ranges(&[3000..3001, 3500..3501], 500).unwrap();
+    assert_eq!((parts[0][0], parts[1][0], source.requests()), (bytes[3000], bytes[3500], 6));
+    let past_end = 4990..5001;
+    assert_eq!(source.read_ranges(&[past_end], 0).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    let huge = 0..u64::MAX;
+    assert_eq!(source.read_ranges(&[huge], 0).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(source.requests(), 6);
+
+    // every request went over the one kept-alive connection
+    assert_eq!(server.connections.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn http_errors_test() {
+    let server = TestServer::start(vec![1, 2, 3], true);
+    assert!(HttpRandomAccessSource::new(&server.url("/missing.bin")).is_err());
+    assert_eq!(HttpRandomAccessSource::new("https://example.com/file").err().unwrap().kind(), ErrorKind::InvalidInput);
+    assert_eq!(HttpRandomAccessSource::new("http:///file").err().unwrap().kind(), ErrorKind::InvalidInput);
+    assert_eq!(parse_content_range("bytes 10-19/300"), Some((10, 300)));
+    assert_eq!(parse_content_range("bytes */300"), None);
+    assert_eq!(with_port("example.com"), "example.com:80");
+    assert_eq!(with_port("example.com:8080"), "example.com:8080");
+    assert_eq!(with_port("[::1]"), "[::1]:80");
+    assert_eq!(with_port("[::1]:8080"), "[::1]:8080");
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::ErrorKind;
+use std::sync::Mutex;
+
+use memmap2::{Mmap, MmapOptions};
+
+use crate::example::random_access_source::RandomAccessSource;
+
+/// files up to this size are mapped in one go, bigger ones through windows of this size
+const DEFAULT_WINDOW_SIZE: u64 = 1 << 30;
+
+struct Window {
+    start: u64,
+    map: Mmap,
+}
+
+/// A `RandomAccessSource` reading a memory-mapped file, so `get` costs no syscall.
+///
+/// Only one window of the file is mapped at a time; reading outside of it maps the window that holds the position.
+pub struct MmapRandomAccessSource {
+    file: File,
+    length: u64,
+    window_size: u64,
+    window: Mutex<Option<Window>>,
+}
+
+impl MmapRandomAccessSource {
+    pub fn new(path: &str) -> io::Result<MmapRandomAccessSource> {
+        MmapRandomAccessSource::with_window_size(path, DEFAULT_WINDOW_SIZE)
+    }
+
+    pub fn with_window_size(path: &str, window_size: u64) -> io::Result<MmapRandomAccessSource> {
+        if window_size == 0 {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "window size must not be zero"));
+        }
+        let file = OpenOptions::new().read(true).open(path)?;
+        let length = file.metadata()?.len();
+        Ok(MmapRandomAccessSource {
+            file,
+            length,
+            window_size,
+            window: Mutex::new(None),
+        })
+    }
+
+    /// copy the bytes at position out of the window holding it, mapping that window first when needed
+    fn read_window(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        let mut window = self.window.lock().unwrap();
+        let mapped = matches!(&*window, Some(w) if w.start <= position && position < w.start + w.map.len() as u64);
+        if !mapped {
+            let start = position / self.window_size * self.window_size;
+            let len = self.window_size.min(self.length - start) as usize;
+            // the file stays open and read only for the lifetime of the mapping
+            let map = unsafe { MmapOptions::new().offset(start).len(len).map(&self.file)? };
+            *window = Some(Window { start, map });
+        }
+
+        let window = window.as_ref().unwrap();
+        let offset = (position - window.start) as usize;
+        let count = bytes.len().min(window.map.len() - offset);
+        bytes[..count].copy_from_slice(&window.map[offset..offset + count]);
+        Ok(count)
+    }
+}
+
+impl RandomAccessSource for MmapRandomAccessSource {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        if position >= self.length {
+            return Ok(None);
+        }
+        let mut buffer: [u8; 1] = [0];
+        self.read_window(position, &mut buffer)?;
+        Ok(Some(buffer[0]))
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        let mut readed = 0;
+        while readed < bytes.len() && position + (readed as u64) < self.length {
+            readed += self.read_window(position + readed as u64, &mut bytes[readed..])?;
+        }
+        Ok(readed)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.length)
+    }
+}
+
+#[test]
+fn mmap_random_access_test() {
+    use crate::example::random_access_source::{FileRandomAccessSouce, test_file};
+
+    let content: Vec<u8> = (0..3 * 4096 + 100).map(|i| (i % 253) as u8).collect();
+    let path = test_file("mmap", &content);
+    let file = FileRandomAccessSouce::new(&path).unwrap();
+    let whole = MmapRandomAccessSource::new(&path).unwrap();
+    let windowed = MmapRandomAccessSource::with_window_size(&path, 4096).unwrap();
+
+    for source in [&whole, &windowed] {
+        assert_eq!(source.length().unwrap(), file.length().unwrap());
+        for position in [0, 1, 4095, 4096, 8191, 3 * 4096 + 99] {
+            assert_eq!(source.get(position).unwrap(), file.get(position).unwrap());
+        }
+        assert_eq!(source.get(content.len() as u64).unwrap(), None);
+
+        let mut bytes = vec![0u8; 8000];
+        assert_eq!(source.get_by_bytes(4000, &mut bytes).unwrap(), 8000);
+        assert_eq!(&bytes[..], &content[4000..12000]);
+        assert_eq!(source.get_by_bytes(12000, &mut bytes).unwrap(), content.len() - 12000);
+        assert_eq!(source.get_by_bytes(content.len() as u64, &mut bytes).unwrap(), 0);
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn mmap_conformance_test() {
+    use crate::example::random_access_source::{check_conformance, conformance_bytes, test_file};
+
+    let expected = conformance_bytes();
+    let path = test_file("mmap_conformance", &expected);
+    check_conformance(&MmapRandomAccessSource::new(&path).unwrap(), &expected);
+    check_conformance(&MmapRandomAccessSource::with_window_size(&path, 4096).unwrap(), &expected);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn mmap_empty_file_test() {
+    let path = crate::example::random_access_source::test_file("mmap_empty", &[]);
+    let source = MmapRandomAccessSource::new(&path).unwrap();
+    let mut bytes = [0u8; 4];
+    assert_eq!(source.length().unwrap(), 0);
+    assert_eq!(source.get_by_bytes(0, &mut bytes).unwrap(), 0);
+    assert_eq!(source.get(0).unwrap(), None);
+    std::fs::remove_file(path).unwrap();
+}
\ No newline at end of file
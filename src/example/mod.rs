@@ -1 +1,13 @@
-pub mod random_access_source;
\ No newline at end of file
+pub mod random_access_source;
+pub mod mmap_random_access_source;
+pub mod array_random_access_source;
+pub mod window_random_access_source;
+pub mod grouped_random_access_source;
+pub mod cached_random_access_source;
+pub mod random_access_reader;
+pub mod binary_read;
+pub mod async_random_access_source;
+pub mod random_access_sink;
+pub mod parallel;
+pub mod checksum;
+pub mod http_random_access_source;
@@ -0,0 +1,38 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// apply work to every item on up to workers threads, each streaming through its own buffer of buffer_size bytes,
+/// and return the results in the order of the items
+pub fn map_parallel<I, T, F>(items: &[I], workers: usize, buffer_size: usize, work: F) -> Vec<T>
+    where I: Sync, T: Send, F: Fn(&I, &mut [u8]) -> T + Sync {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<T>>>());
+    thread::scope(|scope| {
+        for _ in 0..workers.min(items.len()).max(1) {
+            scope.spawn(|| {
+                let mut buffer = vec![0u8; buffer_size];
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= items.len() {
+                        return;
+                    }
+                    let value = work(&items[index], &mut buffer);
+                    results.lock().unwrap()[index] = Some(value);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|value| value.expect("every item worked on")).collect()
+}
+
+#[test]
+fn map_parallel_test() {
+    let items: Vec<u64> = (0..1000).collect();
+    let doubled = map_parallel(&items, 8, 16, |item, buffer| {
+        assert_eq!(buffer.len(), 16);
+        item * 2
+    });
+    assert_eq!(doubled, items.iter().map(|item| item * 2).collect::<Vec<_>>());
+    assert!(map_parallel(&[] as &[u64], 4, 16, |item, _| *item).is_empty());
+}
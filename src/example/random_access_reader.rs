@@ -0,0 +1,212 @@
+use std::io;
+use std::io::{BufRead, ErrorKind, Read, Seek, SeekFrom};
+use std::sync::Mutex;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+const BUFFER_SIZE: usize = 8192;
+
+/// A cursor over a `RandomAccessSource`, so the source can be used with any `std::io` reading code.
+///
+/// Reads go through an internal buffer that is kept across seeks as long as the new position falls inside it.
+pub struct RandomAccessReader<S: RandomAccessSource> {
+    source: S,
+    position: u64,
+    buffer: Vec<u8>,
+    buffer_start: u64,
+    buffer_len: usize,
+}
+
+impl<S: RandomAccessSource> RandomAccessReader<S> {
+    pub fn new(source: S) -> RandomAccessReader<S> {
+        RandomAccessReader::with_capacity(source, BUFFER_SIZE)
+    }
+
+    pub fn with_capacity(source: S, capacity: usize) -> RandomAccessReader<S> {
+        RandomAccessReader {
+            source,
+            position: 0,
+            buffer: vec![0u8; capacity.max(1)],
+            buffer_start: 0,
+            buffer_len: 0,
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn buffered(&self) -> bool {
+        self.buffer_start <= self.position && self.position < self.buffer_start + self.buffer_len as u64
+    }
+}
+
+impl<S: RandomAccessSource> Read for RandomAccessReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // reads at least as big as the buffer skip it
+        if !self.buffered() && buf.len() >= self.buffer.len() {
+            let readed = self.source.get_by_bytes(self.position, buf)?;
+            self.position += readed as u64;
+            return Ok(readed);
+        }
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<S: RandomAccessSource> BufRead for RandomAccessReader<S> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.buffered() {
+            self.buffer_start = self.position;
+            self.buffer_len = self.source.get_by_bytes(self.position, &mut self.buffer)?;
+        }
+        let offset = (self.position - self.buffer_start) as usize;
+        Ok(&self.buffer[offset..self.buffer_len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt as u64;
+    }
+}
+
+impl<S: RandomAccessSource> Seek for RandomAccessReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.source.length()?.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")),
+        }
+    }
+}
+
+/// A `RandomAccessSource` over anything that is `Read + Seek`, seeking before every read.
+pub struct ReadSeekRandomAccessSource<R: Read + Seek> {
+    inner: Mutex<R>,
+    length: u64,
+}
+
+impl<R: Read + Seek> ReadSeekRandomAccessSource<R> {
+    pub fn new(mut inner: R) -> io::Result<ReadSeekRandomAccessSource<R>> {
+        let length = inner.seek(SeekFrom::End(0))?;
+        Ok(ReadSeekRandomAccessSource { inner: Mutex::new(inner), length })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner().unwrap()
+    }
+}
+
+impl<R: Read + Seek> RandomAccessSource for ReadSeekRandomAccessSource<R> {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        let mut buffer: [u8; 1] = [0];
+        match self.get_by_bytes(position, &mut buffer)? {
+            0 => Ok(None),
+            _ => Ok(Some(buffer[0])),
+        }
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.seek(SeekFrom::Start(position))?;
+        let mut readed = 0;
+        while readed < bytes.len() {
+            match inner.read(&mut bytes[readed..]) {
+                Ok(0) => break,
+                Ok(count) => readed += count,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(readed)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.length)
+    }
+}
+
+#[test]
+fn random_access_reader_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let source = ArrayRandomAccessSource::new(&b"first line\nsecond line\nthird"[..]);
+    let mut reader = RandomAccessReader::with_capacity(source, 4);
+    let lines: Vec<String> = (&mut reader).lines().map(|line| line.unwrap()).collect();
+    assert_eq!(lines, vec!["first line", "second line", "third"]);
+
+    assert_eq!(reader.seek(SeekFrom::End(-5)).unwrap(), 23);
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "third");
+
+    reader.seek(SeekFrom::Start(6)).unwrap();
+    assert_eq!(reader.seek(SeekFrom::Current(-2)).unwrap(), 4);
+    let mut word = [0u8; 6];
+    reader.read_exact(&mut word).unwrap();
+    assert_eq!(&word, b"t line");
+    assert!(reader.seek(SeekFrom::Current(-100)).is_err());
+    assert_eq!(reader.position(), 10);
+
+    reader.seek(SeekFrom::Start(100)).unwrap();
+    assert_eq!(reader.read(&mut word).unwrap(), 0);
+}
+
+#[test]
+fn read_seek_conformance_test() {
+    use std::io::Cursor;
+    use crate::example::random_access_source::{check_conformance, conformance_bytes, test_file};
+
+    let bytes = conformance_bytes();
+    check_conformance(&ReadSeekRandomAccessSource::new(Cursor::new(bytes.clone())).unwrap(), &bytes);
+    let path = test_file("read_seek", &bytes);
+    let file = std::fs::File::open(&path).unwrap();
+    check_conformance(&ReadSeekRandomAccessSource::new(file).unwrap(), &bytes);
+    std::fs::remove_file(path).unwrap();
+
+    // short reads of the wrapped reader never show through
+    let trickle = ReadSeekRandomAccessSource::new(Trickle(Cursor::new(bytes.clone()))).unwrap();
+    check_conformance(&trickle, &bytes);
+
+    // round trip: a reader over the source wrapping a reader reads the same bytes back
+    let source = ReadSeekRandomAccessSource::new(Cursor::new(bytes.clone())).unwrap();
+    let mut copied = Vec::new();
+    RandomAccessReader::new(source).read_to_end(&mut copied).unwrap();
+    assert_eq!(copied, bytes);
+}
+
+/// a reader returning at most 3 bytes per read
+#[cfg(test)]
+struct Trickle<R>(R);
+
+#[cfg(test)]
+impl<R: Read> Read for Trickle<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = buf.len().min(3);
+        self.0.read(&mut buf[..length])
+    }
+}
+
+#[cfg(test)]
+impl<R: Seek> Seek for Trickle<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,196 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+
+/// The writable counterpart of `RandomAccessSource`: bytes written at any position, so earlier parts of an output can
+/// be patched once later parts are known, e.g. offsets of a PDF xref table or a ZIP central directory.
+pub trait RandomAccessSink {
+    /// write all of bytes at position. writing past the end grows the sink, with zeros filling any gap
+    fn write_at(&mut self, position: u64, bytes: &[u8]) -> io::Result<()>;
+
+    /// length of this sink
+    fn length(&self) -> io::Result<u64>;
+
+    /// cut the sink to length, or grow it with zeros
+    fn set_len(&mut self, length: u64) -> io::Result<()>;
+
+    /// push buffered writes to the underlying storage
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// make the written data durable, for sinks that have a notion of durability
+    fn sync(&mut self) -> io::Result<()>;
+
+    /// cut the sink to length if it is longer, never growing it
+    fn truncate(&mut self, length: u64) -> io::Result<()> {
+        if length < self.length()? {
+            self.set_len(length)?;
+        }
+        Ok(())
+    }
+
+    /// write bytes at the end of the sink, returning the position they start at
+    fn append(&mut self, bytes: &[u8]) -> io::Result<u64> {
+        let position = self.length()?;
+        self.write_at(position, bytes)?;
+        Ok(position)
+    }
+}
+
+pub struct FileRandomAccessSink {
+    file: File,
+}
+
+impl FileRandomAccessSink {
+    /// open path for writing in place, creating it when missing and keeping its content
+    pub fn new(path: &str) -> io::Result<FileRandomAccessSink> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        Ok(FileRandomAccessSink { file })
+    }
+
+    /// create path empty, truncating it when it exists
+    pub fn create(path: &str) -> io::Result<FileRandomAccessSink> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        Ok(FileRandomAccessSink { file })
+    }
+
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+}
+
+impl RandomAccessSink for FileRandomAccessSink {
+    fn write_at(&mut self, position: u64, bytes: &[u8]) -> io::Result<()> {
+        write_end(position, bytes)?;
+        self.file.write_all_at(bytes, position)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    fn set_len(&mut self, length: u64) -> io::Result<()> {
+        self.file.set_len(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+/// A sink writing into a `Vec<u8>`.
+#[derive(Debug, Default)]
+pub struct ArrayRandomAccessSink {
+    bytes: Vec<u8>,
+}
+
+impl ArrayRandomAccessSink {
+    pub fn new() -> ArrayRandomAccessSink {
+        ArrayRandomAccessSink::default()
+    }
+
+    /// a sink patching bytes in place
+    pub fn from_vec(bytes: Vec<u8>) -> ArrayRandomAccessSink {
+        ArrayRandomAccessSink { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl RandomAccessSink for ArrayRandomAccessSink {
+    fn write_at(&mut self, position: u64, bytes: &[u8]) -> io::Result<()> {
+        let end = usize::try_from(write_end(position, bytes)?)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "position does not fit in memory"))?;
+        let start = end - bytes.len();
+        if end > self.bytes.len() {
+            self.bytes.resize(end, 0);
+        }
+        self.bytes[start..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.bytes.len() as u64)
+    }
+
+    fn set_len(&mut self, length: u64) -> io::Result<()> {
+        let length = usize::try_from(length)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "length does not fit in memory"))?;
+        self.bytes.resize(length, 0);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// the position just past bytes written at position, an error when that is beyond what a position can hold
+fn write_end(position: u64, bytes: &[u8]) -> io::Result<u64> {
+    position.checked_add(bytes.len() as u64)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "write ends past the largest position"))
+}
+
+/// Behaviour every `RandomAccessSink` implementation must have, read_back returning everything written so far.
+#[cfg(test)]
+fn check_sink<S: RandomAccessSink>(sink: &mut S, read_back: impl Fn(&S) -> Vec<u8>) {
+    // back-patching: reserve room for an offset, write the body, then fill the offset in
+    sink.write_at(0, b"HEAD\0\0\0\0").unwrap();
+    let body = sink.append(b"body").unwrap();
+    assert_eq!(body, 8);
+    sink.write_at(4, &(body as u32).to_be_bytes()).unwrap();
+    assert_eq!(read_back(sink), b"HEAD\0\0\0\x08body");
+
+    sink.write_at(14, b"!").unwrap();
+    assert_eq!(read_back(sink), b"HEAD\0\0\0\x08body\0\0!");
+    sink.truncate(100).unwrap();
+    assert_eq!(sink.length().unwrap(), 15);
+    sink.truncate(12).unwrap();
+    assert_eq!(read_back(sink), b"HEAD\0\0\0\x08body");
+    sink.set_len(14).unwrap();
+    assert_eq!(read_back(sink), b"HEAD\0\0\0\x08body\0\0");
+    assert_eq!(sink.write_at(u64::MAX, b"!").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(sink.length().unwrap(), 14);
+    sink.flush().unwrap();
+    sink.sync().unwrap();
+}
+
+#[test]
+fn array_sink_test() {
+    let mut sink = ArrayRandomAccessSink::new();
+    check_sink(&mut sink, |sink| sink.as_bytes().to_vec());
+
+    let mut patched = ArrayRandomAccessSink::from_vec(b"hello rust".to_vec());
+    patched.write_at(6, b"RUST").unwrap();
+    assert_eq!(patched.into_inner(), b"hello RUST");
+}
+
+#[test]
+fn file_sink_test() {
+    use crate::example::random_access_source::test_file;
+
+    let path = test_file("sink", b"old content");
+    let mut sink = FileRandomAccessSink::create(&path).unwrap();
+    check_sink(&mut sink, |_| std::fs::read(&path).unwrap());
+
+    // opening without create keeps the content so it can be patched in place
+    let mut sink = FileRandomAccessSink::new(&path).unwrap();
+    sink.write_at(0, b"BODY").unwrap();
+    sink.sync().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"BODY\0\0\0\x08body\0\0");
+    std::fs::remove_file(path).unwrap();
+}
\ No newline at end of file
@@ -1,21 +1,64 @@
-use std::error::Error;
 use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::io::{ErrorKind, Read};
 use std::os::unix::fs::FileExt;
+use std::sync::Arc;
 
+/// A source of bytes that can be read at any position.
+///
+/// Reaching the end of the source is not an error: `get` returns `None` and `get_by_bytes` returns fewer bytes than
+/// asked for. A short read never happens anywhere else, implementations keep reading until the buffer is full or the
+/// source has ended.
 pub trait RandomAccessSource {
-    /// get a u8 from specified position
-    fn get(&self, position: u64) -> io::Result<u8>;
+    /// get a u8 from specified position, `None` at or after the end of the source
+    fn get(&self, position: u64) -> io::Result<Option<u8>>;
 
-    /// Gets an array at the specified position.  If the number of bytes requested cannot be read, the bytes that can be
-    /// read will be placed in bytes and the number actually read will be returned.
+    /// Gets an array at the specified position. Fewer bytes than requested are read only when the source ends before
+    /// bytes is full, and the number actually read is returned (0 at or after the end of the source).
     fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize>;
 
     /// length of this source
-    fn length(&self) -> u64;
+    fn length(&self) -> io::Result<u64>;
+
+    /// fill bytes from position, failing with `UnexpectedEof` when the source ends first
+    fn read_fully_at(&self, position: u64, bytes: &mut [u8]) -> io::Result<()> {
+        let mut readed = 0;
+        while readed < bytes.len() {
+            match self.get_by_bytes(position + readed as u64, &mut bytes[readed..])? {
+                0 => return Err(io::Error::new(ErrorKind::UnexpectedEof,
+                                               format!("source ended at {}", position + readed as u64))),
+                count => readed += count,
+            }
+        }
+        Ok(())
+    }
 }
 
+macro_rules! forward_random_access_source {
+    ($($pointer:ty),*) => {$(
+        impl<S: RandomAccessSource + ?Sized> RandomAccessSource for $pointer {
+            fn get(&self, position: u64) -> io::Result<Option<u8>> {
+                (**self).get(position)
+            }
+
+            fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+                (**self).get_by_bytes(position, bytes)
+            }
+
+            fn length(&self) -> io::Result<u64> {
+                (**self).length()
+            }
+
+            fn read_fully_at(&self, position: u64, bytes: &mut [u8]) -> io::Result<()> {
+                (**self).read_fully_at(position, bytes)
+            }
+        }
+    )*};
+}
+
+// a shared or boxed source is a source too, so wrappers can own, borrow or share the source they wrap
+forward_random_access_source!(&S, Box<S>, Arc<S>);
+
 pub struct FileRandomAccessSouce {
     file: File,
 }
@@ -40,22 +83,88 @@ impl FileRandomAccessSouce {
 const BUFFER_SIZE: usize = 8192;
 
 impl RandomAccessSource for FileRandomAccessSouce {
-    fn get(&self, position: u64) -> io::Result<u8> {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
         let mut buffer: [u8; 1] = [0];
-        self.file.read_at(&mut buffer, position)?;
-        Ok(buffer[0])
+        match self.get_by_bytes(position, &mut buffer)? {
+            0 => Ok(None),
+            _ => Ok(Some(buffer[0])),
+        }
     }
 
     fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
-        self.file.read_at(bytes, position)
+        // read_at may return less than asked for in the middle of the file, so keep reading until the end of it
+        let mut readed = 0;
+        while readed < bytes.len() {
+            match self.file.read_at(&mut bytes[readed..], position + readed as u64) {
+                Ok(0) => break,
+                Ok(count) => readed += count,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(readed)
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+}
+
+/// write bytes to a scratch file for a test
+#[cfg(test)]
+pub(crate) fn test_file(name: &str, bytes: &[u8]) -> String {
+    let path = std::env::temp_dir().join(format!("random_access_{}_{}", name, std::process::id()));
+    std::fs::write(&path, bytes).expect("write test file error");
+    path.to_str().unwrap().to_string()
+}
+
+/// Conformance suite every `RandomAccessSource` implementation must pass, for a source holding exactly expected.
+#[cfg(test)]
+pub(crate) fn check_conformance<S: RandomAccessSource>(source: &S, expected: &[u8]) {
+    let length = expected.len() as u64;
+    assert_eq!(source.length().unwrap(), length);
+    for position in (0..length).step_by(97).chain(length.checked_sub(1)) {
+        assert_eq!(source.get(position).unwrap(), Some(expected[position as usize]), "get({})", position);
     }
+    assert_eq!(source.get(length).unwrap(), None);
+    assert_eq!(source.get(length + 10).unwrap(), None);
 
-    fn length(&self) -> u64 {
-        match self.file.metadata() {
-            Ok(metadata) => metadata.len(),
-            Err(_) => 0,
+    let mut all = vec![0u8; expected.len()];
+    assert_eq!(source.get_by_bytes(0, &mut all).unwrap(), expected.len());
+    assert_eq!(all, expected);
+
+    for size in [0, 1, 7, 4096] {
+        let mut bytes = vec![0u8; size];
+        for position in [0, 1, length / 2, length.saturating_sub(3)] {
+            // short reads only happen at the end of the source
+            let readed = source.get_by_bytes(position, &mut bytes).unwrap();
+            let start = position.min(length) as usize;
+            assert_eq!(readed, size.min(expected.len() - start), "get_by_bytes({}, {})", position, size);
+            assert_eq!(&bytes[..readed], &expected[start..start + readed]);
         }
+        assert_eq!(source.get_by_bytes(length, &mut bytes).unwrap(), 0);
+        assert_eq!(source.get_by_bytes(length + 10, &mut bytes).unwrap(), 0);
     }
+
+    let mut bytes = vec![0u8; expected.len().min(100)];
+    source.read_fully_at(length - bytes.len() as u64, &mut bytes).unwrap();
+    assert_eq!(bytes, &expected[expected.len() - bytes.len()..]);
+    let error = source.read_fully_at(length.saturating_sub(1), &mut [0u8; 2]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+}
+
+/// the bytes the conformance suite is usually run over
+#[cfg(test)]
+pub(crate) fn conformance_bytes() -> Vec<u8> {
+    (0..10000u32).map(|i| (i * 31 % 256) as u8).collect()
+}
+
+#[test]
+fn file_conformance_test() {
+    let expected = conformance_bytes();
+    let path = test_file("conformance", &expected);
+    check_conformance(&FileRandomAccessSouce::new(&path).unwrap(), &expected);
+    std::fs::remove_file(path).unwrap();
 }
 
 #[test]
@@ -70,7 +179,26 @@ fn file_random_access_test() {
     let readed = file.get_by_bytes(0, &mut bytes).unwrap_or_else(|error| {
         panic!("{}", error);
     });
-    println!("{}", byte);
+    println!("{:?}", byte);
+    assert_eq!(byte, Some(b'h'));
+    assert_eq!(readed as u64, file.length().unwrap());
+}
+
+#[test]
+fn file_end_of_source_test() {
+    let path = test_file("end_of_source", b"hello rust");
+    let file = FileRandomAccessSouce::new(&path).unwrap();
+    assert_eq!(file.get(9).unwrap(), Some(b't'));
+    // reading past the end used to return Ok(0) as if the byte were zero
+    assert_eq!(file.get(10).unwrap(), None);
+    assert_eq!(file.length().unwrap(), 10);
+
+    let mut bytes = [0u8; 4];
+    file.read_fully_at(6, &mut bytes).unwrap();
+    assert_eq!(&bytes, b"rust");
+    let error = file.read_fully_at(7, &mut bytes).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    std::fs::remove_file(path).unwrap();
 }
 
 #[test]
@@ -80,6 +208,6 @@ fn file_random_read_all_test() {
     });
 
     let bytes = file.read_to_vec().expect("file read error");
-    let length = file.length();
+    let length = file.length().expect("file length error");
     println!("{}-{}", bytes.len(), length);
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+use std::io;
+use std::io::ErrorKind;
+
+use crate::example::random_access_source::RandomAccessSource;
+
+/// A sub-range `[offset, offset + length)` of another source, seen as a source of its own starting at position zero.
+///
+/// Reads never reach outside of the range. Windows of a window are taken with `window`, which adds the offsets up
+/// and wraps the same inner source, so nesting costs no extra indirection.
+#[derive(Clone)]
+pub struct WindowRandomAccessSource<S: RandomAccessSource> {
+    source: S,
+    offset: u64,
+    length: u64,
+}
+
+impl<S: RandomAccessSource> WindowRandomAccessSource<S> {
+    pub fn new(source: S, offset: u64, length: u64) -> io::Result<WindowRandomAccessSource<S>> {
+        match offset.checked_add(length) {
+            Some(end) if end <= source.length()? => Ok(WindowRandomAccessSource { source, offset, length }),
+            _ => Err(io::Error::new(ErrorKind::InvalidInput, "window is outside of the source")),
+        }
+    }
+
+    /// the window `[offset, offset + length)` of this window
+    pub fn window(&self, offset: u64, length: u64) -> io::Result<WindowRandomAccessSource<S>>
+        where S: Clone {
+        match offset.checked_add(length) {
+            Some(end) if end <= self.length => Ok(WindowRandomAccessSource {
+                source: self.source.clone(),
+                offset: self.offset + offset,
+                length,
+            }),
+            _ => Err(io::Error::new(ErrorKind::InvalidInput, "window is outside of the source")),
+        }
+    }
+
+    /// position of this window in the wrapped source
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: RandomAccessSource> RandomAccessSource for WindowRandomAccessSource<S> {
+    fn get(&self, position: u64) -> io::Result<Option<u8>> {
+        if position >= self.length {
+            return Ok(None);
+        }
+        self.source.get(self.offset + position)
+    }
+
+    fn get_by_bytes(&self, position: u64, bytes: &mut [u8]) -> io::Result<usize> {
+        if position >= self.length {
+            return Ok(0);
+        }
+        let count = bytes.len().min((self.length - position).min(usize::MAX as u64) as usize);
+        self.source.get_by_bytes(self.offset + position, &mut bytes[..count])
+    }
+
+    fn length(&self) -> io::Result<u64> {
+        Ok(self.length)
+    }
+}
+
+#[test]
+fn window_conformance_test() {
+    use std::sync::Arc;
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+    use crate::example::random_access_source::{check_conformance, conformance_bytes};
+
+    let bytes = conformance_bytes();
+    let mut padded = vec![0xffu8; 300];
+    padded.extend_from_slice(&bytes);
+    padded.extend_from_slice(&[0xee; 500]);
+    let source = ArrayRandomAccessSource::new(padded);
+
+    let window = WindowRandomAccessSource::new(&source, 300, bytes.len() as u64).unwrap();
+    check_conformance(&window, &bytes);
+
+    let shared = WindowRandomAccessSource::new(Arc::new(source), 100, 10500).unwrap();
+    let nested = shared.window(200, bytes.len() as u64).unwrap();
+    assert_eq!(nested.offset(), 300);
+    check_conformance(&nested, &bytes);
+    check_conformance(&nested.window(10, 20).unwrap(), &bytes[10..30]);
+}
+
+#[test]
+fn window_bounds_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let source = ArrayRandomAccessSource::new(&b"0123456789"[..]);
+    assert!(WindowRandomAccessSource::new(&source, 4, 7).is_err());
+    assert!(WindowRandomAccessSource::new(&source, u64::MAX, 2).is_err());
+
+    let window = WindowRandomAccessSource::new(&source, 2, 5).unwrap();
+    assert_eq!(window.get(0).unwrap(), Some(b'2'));
+    assert_eq!(window.get(5).unwrap(), None);
+    let mut bytes = [0u8; 8];
+    assert_eq!(window.get_by_bytes(3, &mut bytes).unwrap(), 2);
+    assert_eq!(&bytes[..2], b"56");
+    assert!(window.window(3, 3).is_err());
+    assert_eq!(window.window(4, 1).unwrap().get(0).unwrap(), Some(b'6'));
+}
\ No newline at end of file
@@ -0,0 +1,303 @@
+use std::error::Error;
+use std::io;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::ops::Range;
+
+use crate::example::binary_read::Endian;
+use crate::example::cached_random_access_source::CachedRandomAccessSource;
+use crate::example::random_access_source::{FileRandomAccessSouce, RandomAccessSource};
+
+/// the file is read in cached pages of this size, big enough that a search costs few reads of the file
+const PAGE_SIZE: usize = 64 * 1024;
+const PAGE_CAPACITY: usize = 64;
+/// a search reads this much at a time, on top of the bytes kept to find matches across reads
+const SEARCH_BUFFER_SIZE: usize = 256 * 1024;
+/// widest line and largest group accepted, anything wider is no longer readable as a dump
+const MAX_COLUMNS: u64 = 256;
+
+/// how `dump` lays out every line
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpFormat {
+    /// bytes per line
+    pub columns: usize,
+    /// bytes per group, groups are separated by a space
+    pub group: usize,
+    /// `Little` shows every group as a little endian number, its bytes reversed
+    pub endian: Endian,
+}
+
+impl Default for DumpFormat {
+    fn default() -> DumpFormat {
+        DumpFormat { columns: 16, group: 2, endian: Endian::Big }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Dump(DumpFormat),
+    /// report every offset of the pattern
+    Find(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct Config {
+    filename: String,
+    offset: u64,
+    length: Option<u64>,
+    command: Command,
+}
+
+impl Config {
+    /// `hex_dump [-s offset] [-l length] [-c columns] [-g group] [-e] [-x hex | -t text] file`.
+    /// numbers are decimal or `0x` hex; `-x` and `-t` search for a pattern instead of dumping
+    pub fn new<I: Iterator<Item = String>>(mut args: I) -> Result<Config, &'static str> {
+        args.next();
+
+        let mut format = DumpFormat::default();
+        let mut pattern = None;
+        let mut offset = 0;
+        let mut length = None;
+        let mut filename = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-s" => offset = parse_number(args.next()).ok_or("Invalid offset")?,
+                "-l" => length = Some(parse_number(args.next()).ok_or("Invalid length")?),
+                "-c" => format.columns = parse_number(args.next()).filter(|columns| (1..=MAX_COLUMNS).contains(columns))
+                    .ok_or("Invalid column count")? as usize,
+                "-g" => format.group = parse_number(args.next()).filter(|group| (1..=MAX_COLUMNS).contains(group))
+                    .ok_or("Invalid group size")? as usize,
+                "-e" => format.endian = Endian::Little,
+                "-x" => pattern = Some(args.next().as_deref().and_then(parse_hex).ok_or("Invalid hex pattern")?),
+                "-t" => pattern = Some(args.next().map(String::into_bytes).ok_or("Didn't get a text pattern")?),
+                _ if arg.starts_with('-') => return Err("Unknown option"),
+                _ => filename = Some(arg),
+            }
+        }
+        let filename = filename.ok_or("Didn't get a file name")?;
+        let command = match pattern {
+            Some(pattern) if pattern.is_empty() => return Err("Empty pattern"),
+            Some(pattern) => Command::Find(pattern),
+            None => Command::Dump(format),
+        };
+        Ok(Config { filename, offset, length, command })
+    }
+}
+
+pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let file = FileRandomAccessSouce::new(&config.filename)?;
+    let source = CachedRandomAccessSource::with_page_size(file, PAGE_SIZE, PAGE_CAPACITY)?;
+    let file_length = source.length()?;
+    let start = config.offset.min(file_length);
+    let end = config.length.map_or(file_length, |length| start.saturating_add(length).min(file_length));
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    match &config.command {
+        Command::Dump(format) => dump(&source, start..end, format, &mut out)?,
+        Command::Find(pattern) => {
+            let mut result = Ok(());
+            find_pattern(&source, start..end, pattern, |offset| {
+                if result.is_ok() {
+                    result = writeln!(out, "{:08x}", offset);
+                }
+            })?;
+            result?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// a number in decimal or with a `0x` prefix in hex
+fn parse_number(arg: Option<String>) -> Option<u64> {
+    let arg = arg?;
+    match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    }
+}
+
+/// bytes written as hex digits, optionally separated by spaces and with a `0x` prefix
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    let digits: Vec<u8> = text.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// write range of source as lines of offset, hex groups and the printable ASCII of the bytes
+pub fn dump<S: RandomAccessSource + ?Sized, W: Write>(source: &S, range: Range<u64>, format: &DumpFormat, out: &mut W)
+                                                      -> io::Result<()> {
+    if format.columns == 0 || format.group == 0 {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "columns and group must not be zero"));
+    }
+    // the hex of a full line, so a short last line still lines its gutter up
+    let hex_width = format.columns * 2 + format.columns.div_ceil(format.group) - 1;
+    let mut bytes = vec![0u8; format.columns];
+    let mut line = String::with_capacity(hex_width + format.columns + 16);
+    let mut position = range.start;
+    while position < range.end {
+        let count = (range.end - position).min(format.columns as u64) as usize;
+        let bytes = &mut bytes[..count];
+        source.read_fully_at(position, bytes)?;
+
+        line.clear();
+        line.push_str(&format!("{:08x}: ", position));
+        let hex_start = line.len();
+        for (index, group) in bytes.chunks(format.group).enumerate() {
+            if index > 0 {
+                line.push(' ');
+            }
+            // a short last group is padded where its missing bytes would be, as xxd does
+            let missing = format.group - group.len();
+            match format.endian {
+                Endian::Big => group.iter().for_each(|byte| push_hex(&mut line, *byte)),
+                Endian::Little => {
+                    line.push_str(&"  ".repeat(missing));
+                    group.iter().rev().for_each(|byte| push_hex(&mut line, *byte));
+                }
+            }
+        }
+        let hex_length = line.len() - hex_start;
+        line.push_str(&" ".repeat(hex_width - hex_length + 2));
+        line.extend(bytes.iter().map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' }));
+        writeln!(out, "{}", line)?;
+        position += count as u64;
+    }
+    Ok(())
+}
+
+fn push_hex(line: &mut String, byte: u8) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    line.push(DIGITS[(byte >> 4) as usize] as char);
+    line.push(DIGITS[(byte & 0xF) as usize] as char);
+}
+
+/// call found with the offset of every occurrence of pattern in range of source, overlapping ones included, in order
+pub fn find_pattern<S, F>(source: &S, range: Range<u64>, pattern: &[u8], mut found: F) -> io::Result<()>
+    where S: RandomAccessSource + ?Sized, F: FnMut(u64) {
+    if pattern.is_empty() {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "pattern must not be empty"));
+    }
+    // the last pattern.len() - 1 bytes of a read are searched again with the next one
+    let overlap = pattern.len() - 1;
+    let mut buffer = vec![0u8; SEARCH_BUFFER_SIZE.max(pattern.len() * 2)];
+    let mut kept = 0;
+    // position of buffer[0] in the source
+    let mut start = range.start;
+    while start + (kept as u64) < range.end {
+        let count = (range.end - start - kept as u64).min((buffer.len() - kept) as u64) as usize;
+        source.read_fully_at(start + kept as u64, &mut buffer[kept..kept + count])?;
+        let filled = kept + count;
+        let data = &buffer[..filled];
+        let mut from = 0;
+        while let Some(index) = data[from..].iter().position(|byte| *byte == pattern[0]) {
+            let at = from + index;
+            if at + pattern.len() > filled {
+                break;
+            }
+            if &data[at..at + pattern.len()] == pattern {
+                found(start + at as u64);
+            }
+            from = at + 1;
+        }
+        kept = overlap.min(filled);
+        buffer.copy_within(filled - kept..filled, 0);
+        start += (filled - kept) as u64;
+    }
+    Ok(())
+}
+
+#[test]
+fn config_test() {
+    let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>().into_iter();
+
+    let config = Config::new(args("hex_dump -s 0x10 -l 32 -c 8 -g 4 -e data.bin")).unwrap();
+    assert_eq!((config.filename.as_str(), config.offset, config.length), ("data.bin", 16, Some(32)));
+    assert_eq!(config.command, Command::Dump(DumpFormat { columns: 8, group: 4, endian: Endian::Little }));
+    let config = Config::new(args("hex_dump -x CAFE_BABE data.bin"));
+    assert!(config.is_err());
+    let config = Config::new(args("hex_dump -x 0xcafebabe data.bin")).unwrap();
+    assert_eq!(config.command, Command::Find(vec![0xca, 0xfe, 0xba, 0xbe]));
+    let config = Config::new(args("hex_dump -t PK data.bin")).unwrap();
+    assert_eq!(config.command, Command::Find(b"PK".to_vec()));
+
+    assert_eq!(Config::new(args("hex_dump -g 0 data.bin")).unwrap_err(), "Invalid group size");
+    assert_eq!(Config::new(args("hex_dump -g 257 data.bin")).unwrap_err(), "Invalid group size");
+    assert_eq!(Config::new(args("hex_dump -c 0xFFFFFFFFFFFF data.bin")).unwrap_err(), "Invalid column count");
+    assert!(Config::new(args("hex_dump -c 256 -g 256 data.bin")).is_ok());
+    assert_eq!(Config::new(args("hex_dump -s")).unwrap_err(), "Invalid offset");
+    assert_eq!(Config::new(args("hex_dump -q data.bin")).unwrap_err(), "Unknown option");
+    assert_eq!(Config::new(args("hex_dump -e")).unwrap_err(), "Didn't get a file name");
+    assert_eq!(parse_hex("de ad be ef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+    assert_eq!(parse_hex("abc"), None);
+}
+
+#[test]
+fn dump_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let source = ArrayRandomAccessSource::new(b"Hello, hex dump!\x00\x01\x02\xffend".to_vec());
+    let dumped = |range: Range<u64>, format: &DumpFormat| {
+        let mut out = Vec::new();
+        dump(&source, range, format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(dumped(0..23, &DumpFormat::default()), "\
+00000000: 4865 6c6c 6f2c 2068 6578 2064 756d 7021  Hello, hex dump!
+00000010: 0001 02ff 656e 64                        ....end
+");
+    let little = DumpFormat { columns: 8, group: 4, endian: Endian::Little };
+    assert_eq!(dumped(14..23, &little), "\
+0000000e: 01002170 6e65ff02  p!....en
+00000016:       64           d
+");
+    assert_eq!(dumped(5..5, &DumpFormat::default()), "");
+    let odd = DumpFormat { columns: 5, group: 3, endian: Endian::Big };
+    assert_eq!(dumped(0..7, &odd), "00000000: 48656c 6c6f  Hello\n00000005: 2c20         , \n");
+    assert!(dump(&source, 0..40, &DumpFormat::default(), &mut Vec::new()).is_err());
+}
+
+#[test]
+fn find_pattern_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let find = |bytes: &[u8], range: Range<u64>, pattern: &[u8]| {
+        let source = ArrayRandomAccessSource::new(bytes.to_vec());
+        let mut offsets = Vec::new();
+        find_pattern(&source, range, pattern, |offset| offsets.push(offset)).unwrap();
+        offsets
+    };
+    assert_eq!(find(b"aaaa", 0..4, b"aa"), vec![0, 1, 2]);
+    assert_eq!(find(b"xPKyPKPK", 0..8, b"PK"), vec![1, 4, 6]);
+    assert_eq!(find(b"xPKyPKPK", 2..7, b"PK"), vec![4]);
+    assert_eq!(find(b"abc", 0..3, b"abcd"), Vec::<u64>::new());
+
+    // matches across the buffer boundary
+    let mut bytes = vec![0u8; SEARCH_BUFFER_SIZE * 2 + 100];
+    let marks = [0, SEARCH_BUFFER_SIZE - 3, SEARCH_BUFFER_SIZE * 2 - 1, bytes.len() - 4];
+    for mark in marks {
+        bytes[mark..mark + 4].copy_from_slice(&[0xca, 0xfe, 0xba, 0xbe]);
+    }
+    let expected: Vec<u64> = marks.iter().map(|mark| *mark as u64).collect();
+    assert_eq!(find(&bytes, 0..bytes.len() as u64, &[0xca, 0xfe, 0xba, 0xbe]), expected);
+
+    let source = ArrayRandomAccessSource::new(bytes);
+    assert!(find_pattern(&source, 0..10, b"", |_| {}).is_err());
+}
+
+#[test]
+fn run_test() {
+    use crate::example::random_access_source::test_file;
+
+    let path = test_file("hex_dump", b"\x7fELF and more ELF");
+    let config = Config::new(["hex_dump", "-t", "ELF", path.as_str()].into_iter().map(String::from)).unwrap();
+    run(config).unwrap();
+    let config = Config::new(["hex_dump", "-s", "4", "-l", "100", path.as_str()].into_iter().map(String::from)).unwrap();
+    run(config).unwrap();
+    std::fs::remove_file(path).unwrap();
+}
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::{env, fs, process, thread};
 use std::error::Error;
+use std::path::PathBuf;
 use std::sync::mpsc;
 
 use course::basic::{control_flow, println_int, statement_expression, sum, variables};
@@ -23,6 +24,9 @@ mod example;
 mod course;
 mod search_text;
 mod directory_size;
+mod hex_dump;
+mod pdf;
+mod zip;
 
 fn basic_program() {
     //guessing_number();
@@ -70,4 +74,32 @@ fn search_test() {
     }
 }
 
-fn main() {}
\ No newline at end of file
+fn hex_dump_test<I: Iterator<Item = String>>(args: I) {
+    let config = hex_dump::hex_dump::Config::new(args).unwrap_or_else(|error| {
+        eprintln!("Problem parsing arguments: {}.", error);
+        process::exit(1);
+    });
+
+    if let Err(err) = hex_dump::hex_dump::run(config) {
+        eprintln!("Application error: {}.", err);
+        process::exit(1);
+    }
+}
+
+fn browse_test<I: Iterator<Item = String>>(mut args: I) {
+    let root = args.next().unwrap_or_else(|| ".".to_string());
+    if let Err(err) = directory_size::browser::browse(PathBuf::from(root)) {
+        eprintln!("Application error: {}.", err);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    // `rust_playground hex_dump [options] file` and `rust_playground browse [directory]`, the subcommand
+    // standing in for the program name
+    match env::args().nth(1).as_deref() {
+        Some("hex_dump") => hex_dump_test(env::args().skip(1)),
+        Some("browse") => browse_test(env::args().skip(2)),
+        _ => {}
+    }
+}
\ No newline at end of file
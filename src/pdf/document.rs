@@ -0,0 +1,428 @@
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::example::array_random_access_source::ArrayRandomAccessSource;
+use crate::example::random_access_source::RandomAccessSource;
+use crate::pdf::error::PdfError;
+use crate::pdf::object::{Dictionary, ObjectId, Parser, PdfObject, PdfStream};
+use crate::pdf::filter::{read_stream, FilterRegistry};
+use crate::pdf::xref::{XrefEntry, XrefTable};
+
+/// page attributes a page takes from its ancestors when it does not set them itself
+const INHERITED: [&str; 4] = ["Resources", "MediaBox", "CropBox", "Rotate"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub id: ObjectId,
+    /// the page dictionary with inherited attributes filled in
+    pub dict: Dictionary,
+}
+
+/// An `/ObjStm` stream: its decoded data and where each object in it starts.
+struct ObjectStream {
+    parser: Parser<ArrayRandomAccessSource<Vec<u8>>>,
+    offsets: Vec<(u32, u64)>,
+}
+
+/// A PDF file read lazily: only the cross-reference sections are read up front, every object is parsed the first time
+/// it is asked for and cached afterwards.
+///
+/// Parsing goes through one parser over the source, so a document is meant to be used from one thread.
+pub struct PdfDocument<S: RandomAccessSource> {
+    parser: RefCell<Parser<S>>,
+    xref: XrefTable,
+    filters: FilterRegistry,
+    cache: RefCell<HashMap<ObjectId, Rc<PdfObject>>>,
+    object_streams: RefCell<HashMap<u32, Rc<RefCell<ObjectStream>>>>,
+    /// objects being loaded, a request for one of them means a cycle
+    loading: RefCell<HashSet<ObjectId>>,
+}
+
+impl<S: RandomAccessSource> PdfDocument<S> {
+    pub fn open(source: S) -> Result<PdfDocument<S>, PdfError> {
+        PdfDocument::open_with(source, FilterRegistry::new())
+    }
+
+    /// open with filters decoding the streams
+    pub fn open_with(source: S, filters: FilterRegistry) -> Result<PdfDocument<S>, PdfError> {
+        let mut parser = Parser::new(source);
+        let xref = XrefTable::read_with(&mut parser, &filters)?;
+        Ok(PdfDocument {
+            parser: RefCell::new(parser),
+            xref,
+            filters,
+            cache: RefCell::new(HashMap::new()),
+            object_streams: RefCell::new(HashMap::new()),
+            loading: RefCell::new(HashSet::new()),
+        })
+    }
+
+    pub fn xref(&self) -> &XrefTable {
+        &self.xref
+    }
+
+    pub fn trailer(&self) -> &Dictionary {
+        self.xref.trailer()
+    }
+
+    /// the source the document is read from
+    pub fn source(&self) -> Ref<'_, S> {
+        Ref::map(self.parser.borrow(), |parser| parser.source())
+    }
+
+    /// the object with id, `Null` when the file has no such object as the spec asks
+    pub fn get(&self, id: ObjectId) -> Result<Rc<PdfObject>, PdfError> {
+        if let Some(object) = self.cache.borrow().get(&id) {
+            return Ok(Rc::clone(object));
+        }
+        if !self.loading.borrow_mut().insert(id) {
+            return Err(PdfError::ReferenceCycle(id));
+        }
+        let loaded = self.load(id);
+        self.loading.borrow_mut().remove(&id);
+
+        let object = Rc::new(loaded?);
+        self.cache.borrow_mut().insert(id, Rc::clone(&object));
+        Ok(object)
+    }
+
+    /// follow references until a direct object
+    pub fn resolve(&self, object: &PdfObject) -> Result<Rc<PdfObject>, PdfError> {
+        let mut id = match object {
+            PdfObject::Reference(id) => *id,
+            direct => return Ok(Rc::new(direct.clone())),
+        };
+        let mut visited = HashSet::new();
+        loop {
+            if !visited.insert(id) {
+                return Err(PdfError::ReferenceCycle(id));
+            }
+            let object = self.get(id)?;
+            match *object {
+                PdfObject::Reference(next) => id = next,
+                _ => return Ok(object),
+            }
+        }
+    }
+
+    /// resolve object, which must be a dictionary or a stream
+    pub fn resolve_dict(&self, object: &PdfObject) -> Result<Dictionary, PdfError> {
+        self.resolve(object)?.as_dict().cloned()
+            .ok_or_else(|| PdfError::Malformed(format!("expected a dictionary but found {:?}", object)))
+    }
+
+    /// the decoded data of stream, resolving an indirect `/Length`, `/Filter` or `/DecodeParms`
+    pub fn stream_data(&self, stream: &PdfStream) -> Result<Vec<u8>, PdfError> {
+        let length = match stream.dict.get("Length") {
+            Some(length) => self.resolve(length)?.as_i64(),
+            None => None,
+        };
+        let length = length.filter(|length| *length >= 0)
+            .ok_or_else(|| PdfError::syntax(stream.data_offset, "stream needs a /Length"))?;
+
+        let mut direct = stream.clone();
+        for key in ["Filter", "DecodeParms"] {
+            if let Some(value) = stream.dict.get(key).filter(|value| value.as_reference().is_some()) {
+                direct.dict.insert(key, (*self.resolve(value)?).clone());
+            }
+        }
+        read_stream(self.parser.borrow().source(), &direct, length as u64, &self.filters)
+    }
+
+    fn load(&self, id: ObjectId) -> Result<PdfObject, PdfError> {
+        match self.xref.get(id.number) {
+            Some(XrefEntry::InUse { offset, generation }) if generation == id.generation => {
+                let mut parser = self.parser.borrow_mut();
+                parser.seek(offset)?;
+                let (found, object) = parser.parse_indirect_object()?;
+                if found != id {
+                    return Err(PdfError::syntax(offset, format!("expected object {} {} but found {} {}",
+                                                                id.number, id.generation, found.number, found.generation)));
+                }
+                Ok(object)
+            }
+            // objects in object streams always have generation 0
+            Some(XrefEntry::Compressed { stream, index }) if id.generation == 0 =>
+                self.load_compressed(id.number, stream, index),
+            _ => Ok(PdfObject::Null),
+        }
+    }
+
+    fn load_compressed(&self, number: u32, stream: u32, index: u32) -> Result<PdfObject, PdfError> {
+        let object_stream = self.object_stream(stream)?;
+        let mut object_stream = object_stream.borrow_mut();
+        // trust the index from the xref but fall back to a search when it points at another object
+        let offset = match object_stream.offsets.get(index as usize) {
+            Some((found, offset)) if *found == number => *offset,
+            _ => object_stream.offsets.iter().find(|(found, _)| *found == number).map(|(_, offset)| *offset)
+                .ok_or_else(|| PdfError::Malformed(format!("object {} is not in object stream {}", number, stream)))?,
+        };
+        object_stream.parser.seek(offset)?;
+        object_stream.parser.parse_object()
+    }
+
+    fn object_stream(&self, number: u32) -> Result<Rc<RefCell<ObjectStream>>, PdfError> {
+        if let Some(object_stream) = self.object_streams.borrow().get(&number) {
+            return Ok(Rc::clone(object_stream));
+        }
+        let object = self.get(ObjectId::new(number, 0))?;
+        let stream = object.as_stream().filter(|stream| stream.dict.has_type("ObjStm"))
+            .ok_or_else(|| PdfError::Malformed(format!("object {} is not an object stream", number)))?;
+        let count = stream.dict.get("N").and_then(PdfObject::as_i64).filter(|count| *count >= 0);
+        let first = stream.dict.get("First").and_then(PdfObject::as_i64).filter(|first| *first >= 0);
+        let (count, first) = count.zip(first)
+            .ok_or_else(|| PdfError::Malformed(format!("object stream {} needs /N and /First", number)))?;
+
+        // the data starts with count pairs of object number and offset relative to first
+        let data = self.stream_data(stream)?;
+        // every pair takes at least four bytes, which bounds a broken count
+        let mut offsets = Vec::with_capacity((count as u64).min(data.len() as u64 / 4) as usize);
+        let mut parser = Parser::new(ArrayRandomAccessSource::new(data));
+        let broken = || PdfError::Malformed(format!("object stream {} has a broken header", number));
+        for _ in 0..count {
+            let pair = parser.parse_object()?.as_i64().zip(parser.parse_object()?.as_i64());
+            match pair {
+                Some((object, offset)) if object >= 0 && offset >= 0 => {
+                    let offset = first.checked_add(offset).ok_or_else(broken)?;
+                    offsets.push((object as u32, offset as u64));
+                }
+                _ => return Err(broken()),
+            }
+        }
+        let object_stream = Rc::new(RefCell::new(ObjectStream { parser, offsets }));
+        self.object_streams.borrow_mut().insert(number, Rc::clone(&object_stream));
+        Ok(object_stream)
+    }
+
+    /// the document catalog, `/Root` of the trailer
+    pub fn catalog(&self) -> Result<Dictionary, PdfError> {
+        let root = self.trailer().get("Root")
+            .ok_or_else(|| PdfError::Malformed("trailer has no /Root".to_string()))?;
+        self.resolve_dict(root)
+    }
+
+    /// number of pages, from `/Count` of the root of the page tree when it has one
+    pub fn page_count(&self) -> Result<usize, PdfError> {
+        let root = self.pages_root()?;
+        match self.resolve_dict(&root)?.get("Count").and_then(PdfObject::as_i64) {
+            Some(count) if count >= 0 => Ok(count as usize),
+            _ => Ok(self.pages()?.len()),
+        }
+    }
+
+    /// the page at index, descending only into the subtree holding it
+    pub fn page(&self, index: usize) -> Result<Page, PdfError> {
+        let mut remaining = index;
+        let mut node = self.pages_root()?;
+        let mut inherited = Dictionary::new();
+        let mut visited = HashSet::new();
+        'descend: loop {
+            let id = node_id(&node)?;
+            if !visited.insert(id) {
+                return Err(PdfError::ReferenceCycle(id));
+            }
+            let dict = self.resolve_dict(&node)?;
+            inherit(&mut inherited, &dict);
+            let kids = dict.get("Kids").map(|kids| self.resolve(kids)).transpose()?;
+            let kids = kids.as_ref().and_then(|kids| kids.as_array())
+                .ok_or_else(|| PdfError::Malformed(format!("page tree node {} has no /Kids", id.number)))?;
+            for kid in kids {
+                let kid_dict = self.resolve_dict(kid)?;
+                if is_page(&kid_dict) {
+                    if remaining == 0 {
+                        let mut dict = kid_dict;
+                        fill_inherited(&mut dict, &inherited);
+                        return Ok(Page { id: node_id(kid)?, dict });
+                    }
+                    remaining -= 1;
+                    continue;
+                }
+                let count = kid_dict.get("Count").and_then(PdfObject::as_i64).unwrap_or(0).max(0) as usize;
+                if remaining < count {
+                    node = kid.clone();
+                    continue 'descend;
+                }
+                remaining -= count;
+            }
+            return Err(PdfError::Malformed(format!("no page {} in a document of {} pages", index, index - remaining)));
+        }
+    }
+
+    /// every page in order
+    pub fn pages(&self) -> Result<Vec<Page>, PdfError> {
+        let mut pages = Vec::new();
+        let mut visited = HashSet::new();
+        self.collect_pages(&self.pages_root()?, &Dictionary::new(), &mut visited, &mut pages)?;
+        Ok(pages)
+    }
+
+    fn collect_pages(&self, node: &PdfObject, inherited: &Dictionary, visited: &mut HashSet<ObjectId>,
+                     pages: &mut Vec<Page>) -> Result<(), PdfError> {
+        let id = node_id(node)?;
+        if !visited.insert(id) {
+            return Err(PdfError::ReferenceCycle(id));
+        }
+        let mut dict = self.resolve_dict(node)?;
+        if is_page(&dict) {
+            fill_inherited(&mut dict, inherited);
+            pages.push(Page { id, dict });
+            return Ok(());
+        }
+        let mut inherited = inherited.clone();
+        inherit(&mut inherited, &dict);
+        if let Some(kids) = dict.get("Kids") {
+            let kids = self.resolve(kids)?;
+            for kid in kids.as_array().into_iter().flatten() {
+                self.collect_pages(kid, &inherited, visited, pages)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pages_root(&self) -> Result<PdfObject, PdfError> {
+        self.catalog()?.get("Pages").cloned()
+            .ok_or_else(|| PdfError::Malformed("catalog has no /Pages".to_string()))
+    }
+}
+
+fn node_id(node: &PdfObject) -> Result<ObjectId, PdfError> {
+    node.as_reference().ok_or_else(|| PdfError::Malformed("page tree nodes must be indirect objects".to_string()))
+}
+
+/// a leaf of the page tree, trusting `/Kids` over a missing `/Type`
+fn is_page(dict: &Dictionary) -> bool {
+    dict.has_type("Page") || (!dict.has_type("Pages") && dict.get("Kids").is_none())
+}
+
+fn inherit(inherited: &mut Dictionary, node: &Dictionary) {
+    for key in INHERITED {
+        if let Some(value) = node.get(key) {
+            inherited.insert(key, value.clone());
+        }
+    }
+}
+
+fn fill_inherited(page: &mut Dictionary, inherited: &Dictionary) {
+    for key in INHERITED {
+        if let (None, Some(value)) = (page.get(key), inherited.get(key)) {
+            page.insert(key, value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+fn open(pdf: Vec<u8>) -> PdfDocument<ArrayRandomAccessSource<Vec<u8>>> {
+    PdfDocument::open(ArrayRandomAccessSource::new(pdf)).unwrap()
+}
+
+#[test]
+fn resolve_test() {
+    use crate::pdf::xref::build_pdf;
+
+    let document = open(build_pdf(&[
+        "<< /Type /Catalog >>",
+        "3 0 R",
+        "(target)",
+        "5 0 R",
+        "4 0 R",
+        "<< /Length 7 0 R >>\nstream\nhello stream\nendstream",
+        "12",
+        "<< /Length 11 /Filter 9 0 R >>\nstream\n68656c6c6f>\nendstream",
+        "/AHx",
+    ]));
+    let target = document.get(ObjectId::new(3, 0)).unwrap();
+    assert_eq!(target.as_string(), Some(&b"target"[..]));
+    assert!(Rc::ptr_eq(&target, &document.get(ObjectId::new(3, 0)).unwrap()));
+    assert_eq!(*document.resolve(&PdfObject::Reference(ObjectId::new(2, 0))).unwrap(), PdfObject::String(b"target".to_vec()));
+    assert_eq!(*document.resolve(&PdfObject::Integer(1)).unwrap(), PdfObject::Integer(1));
+
+    // missing objects and wrong generations are null
+    assert_eq!(*document.get(ObjectId::new(99, 0)).unwrap(), PdfObject::Null);
+    assert_eq!(*document.get(ObjectId::new(3, 1)).unwrap(), PdfObject::Null);
+
+    assert!(matches!(document.resolve(&PdfObject::Reference(ObjectId::new(4, 0))), Err(PdfError::ReferenceCycle(_))));
+
+    let stream = document.get(ObjectId::new(6, 0)).unwrap();
+    assert_eq!(document.stream_data(stream.as_stream().unwrap()).unwrap(), b"hello stream");
+    let filtered = document.get(ObjectId::new(8, 0)).unwrap();
+    assert_eq!(document.stream_data(filtered.as_stream().unwrap()).unwrap(), b"hello");
+}
+
+#[test]
+fn object_stream_test() {
+    use crate::pdf::xref::build_pdf;
+
+    let objects = ["<< /Type /Page /Parent 2 0 R >>", "(five)"];
+    let header = format!("4 0 5 {} ", objects[0].len() + 1);
+    let data = format!("{}{} {}", header, objects[0], objects[1]);
+    let build = |counts: &str| {
+        let object_stream = format!("<< /Type /ObjStm {} /Length {} >>\nstream\n{}\nendstream", counts, data.len(), data);
+        let mut pdf = build_pdf(&["<< /Type /Catalog /Pages 2 0 R >>", "<< /Type /Pages /Kids [4 0 R] /Count 1 >>",
+                                  &object_stream]);
+
+        // an xref stream update placing objects 4 and 5 in object stream 3
+        let prev = crate::pdf::xref::find_startxref(&ArrayRandomAccessSource::new(&pdf[..])).unwrap();
+        let six = pdf.len();
+        let rows = [2u8, 0, 3, 0, 2, 0, 3, 1, 1, (six >> 8) as u8, six as u8, 0];
+        pdf.extend_from_slice(format!("6 0 obj\n<< /Type /XRef /W [1 2 1] /Index [4 3] /Size 7 /Root 1 0 R /Prev {} \
+/Length {} >>\nstream\n", prev, rows.len()).as_bytes());
+        pdf.extend_from_slice(&rows);
+        pdf.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF", six).as_bytes());
+        pdf
+    };
+    let pdf = build(&format!("/N 2 /First {}", header.len()));
+
+    let document = open(pdf);
+    assert_eq!(*document.get(ObjectId::new(5, 0)).unwrap(), PdfObject::String(b"five".to_vec()));
+    let pages = document.pages().unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].id, ObjectId::new(4, 0));
+    assert!(pages[0].dict.has_type("Page"));
+
+    // a count or first offset far beyond the data fails on the header instead of allocating or overflowing
+    for counts in [format!("/N {} /First {}", i64::MAX, header.len()), format!("/N 2 /First {}", i64::MAX)] {
+        let document = open(build(&counts));
+        assert!(matches!(document.get(ObjectId::new(5, 0)), Err(PdfError::Malformed(_))));
+    }
+}
+
+#[test]
+fn page_tree_test() {
+    use crate::pdf::xref::build_pdf;
+
+    let document = open(build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R 4 0 R 7 0 R] /Count 4 /MediaBox [0 0 612 792] /Resources << /Font << >> >> >>",
+        "<< /Type /Page /Parent 2 0 R /Contents 8 0 R >>",
+        "<< /Type /Pages /Parent 2 0 R /Kids [5 0 R 6 0 R] /Count 2 /Rotate 90 >>",
+        "<< /Type /Page /Parent 4 0 R >>",
+        "<< /Type /Page /Parent 4 0 R /Rotate 0 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 100 100] >>",
+    ]));
+    assert_eq!(document.page_count().unwrap(), 4);
+    let pages = document.pages().unwrap();
+    let ids: Vec<u32> = pages.iter().map(|page| page.id.number).collect();
+    assert_eq!(ids, vec![3, 5, 6, 7]);
+    for (index, page) in pages.iter().enumerate() {
+        assert_eq!(&document.page(index).unwrap(), page);
+    }
+    assert!(document.page(4).is_err());
+
+    // attributes come from the nearest ancestor unless the page sets them
+    assert_eq!(pages[1].dict.get("Rotate"), Some(&PdfObject::Integer(90)));
+    assert_eq!(pages[2].dict.get("Rotate"), Some(&PdfObject::Integer(0)));
+    assert_eq!(pages[0].dict.get("Rotate"), None);
+    assert!(pages[1].dict.get("Resources").is_some());
+    assert_eq!(pages[3].dict.get("MediaBox").unwrap().as_array().unwrap()[2], PdfObject::Integer(100));
+    assert_eq!(pages[1].dict.get("MediaBox").unwrap().as_array().unwrap()[2], PdfObject::Integer(612));
+
+    // a node listing its parent as a kid is reported instead of walked forever
+    let looped = open(build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Pages /Kids [2 0 R] /Count 1 >>",
+    ]));
+    assert!(matches!(looped.pages(), Err(PdfError::ReferenceCycle(_))));
+    assert!(matches!(looped.page(0), Err(PdfError::ReferenceCycle(_))));
+}
\ No newline at end of file
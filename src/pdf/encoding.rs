@@ -0,0 +1,143 @@
+/// glyph names of 0x20..=0x7E in StandardEncoding, which WinAnsi and MacRoman share apart from 0x27 and 0x60
+const ASCII_NAMES: [&str; 95] = [
+    "space", "exclam", "quotedbl", "numbersign", "dollar", "percent", "ampersand", "quoteright", "parenleft",
+    "parenright", "asterisk", "plus", "comma", "hyphen", "period", "slash", "zero", "one", "two", "three", "four",
+    "five", "six", "seven", "eight", "nine", "colon", "semicolon", "less", "equal", "greater", "question", "at", "A",
+    "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X",
+    "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum", "underscore", "quoteleft", "a", "b", "c", "d",
+    "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z",
+    "braceleft", "bar", "braceright", "asciitilde",
+];
+
+/// glyph names of U+00A0..=U+00FF
+const LATIN1_NAMES: [&str; 96] = [
+    "nbspace", "exclamdown", "cent", "sterling", "currency", "yen", "brokenbar", "section", "dieresis", "copyright",
+    "ordfeminine", "guillemotleft", "logicalnot", "sfthyphen", "registered", "macron", "degree", "plusminus",
+    "twosuperior", "threesuperior", "acute", "mu", "paragraph", "periodcentered", "cedilla", "onesuperior",
+    "ordmasculine", "guillemotright", "onequarter", "onehalf", "threequarters", "questiondown", "Agrave", "Aacute",
+    "Acircumflex", "Atilde", "Adieresis", "Aring", "AE", "Ccedilla", "Egrave", "Eacute", "Ecircumflex", "Edieresis",
+    "Igrave", "Iacute", "Icircumflex", "Idieresis", "Eth", "Ntilde", "Ograve", "Oacute", "Ocircumflex", "Otilde",
+    "Odieresis", "multiply", "Oslash", "Ugrave", "Uacute", "Ucircumflex", "Udieresis", "Yacute", "Thorn",
+    "germandbls", "agrave", "aacute", "acircumflex", "atilde", "adieresis", "aring", "ae", "ccedilla", "egrave",
+    "eacute", "ecircumflex", "edieresis", "igrave", "iacute", "icircumflex", "idieresis", "eth", "ntilde", "ograve",
+    "oacute", "ocircumflex", "otilde", "odieresis", "divide", "oslash", "ugrave", "uacute", "ucircumflex",
+    "udieresis", "yacute", "thorn", "ydieresis",
+];
+
+/// the remaining names the standard encodings use, with a few common ligatures
+const OTHER_NAMES: [(&str, char); 44] = [
+    ("quotesingle", '\''), ("grave", '`'), ("quoteleft", '\u{2018}'), ("quoteright", '\u{2019}'),
+    ("quotedblleft", '\u{201C}'), ("quotedblright", '\u{201D}'), ("quotesinglbase", '\u{201A}'),
+    ("quotedblbase", '\u{201E}'), ("bullet", '\u{2022}'), ("endash", '\u{2013}'), ("emdash", '\u{2014}'),
+    ("ellipsis", '\u{2026}'), ("dagger", '\u{2020}'), ("daggerdbl", '\u{2021}'), ("perthousand", '\u{2030}'),
+    ("trademark", '\u{2122}'), ("Euro", '\u{20AC}'), ("florin", '\u{0192}'), ("fraction", '\u{2044}'),
+    ("guilsinglleft", '\u{2039}'), ("guilsinglright", '\u{203A}'), ("circumflex", '\u{02C6}'), ("tilde", '\u{02DC}'),
+    ("breve", '\u{02D8}'), ("dotaccent", '\u{02D9}'), ("ring", '\u{02DA}'), ("hungarumlaut", '\u{02DD}'),
+    ("ogonek", '\u{02DB}'), ("caron", '\u{02C7}'), ("dotlessi", '\u{0131}'), ("Lslash", '\u{0141}'),
+    ("lslash", '\u{0142}'), ("OE", '\u{0152}'), ("oe", '\u{0153}'), ("Scaron", '\u{0160}'), ("scaron", '\u{0161}'),
+    ("Zcaron", '\u{017D}'), ("zcaron", '\u{017E}'), ("Ydieresis", '\u{0178}'), ("minus", '\u{2212}'),
+    ("fi", '\u{FB01}'), ("fl", '\u{FB02}'), ("ff", '\u{FB00}'), ("ffi", '\u{FB03}'),
+];
+
+/// WinAnsiEncoding 0x80..=0x9F, the rest being Latin-1
+const WIN_ANSI_HIGH: [Option<char>; 32] = [
+    Some('\u{20AC}'), None, Some('\u{201A}'), Some('\u{0192}'), Some('\u{201E}'), Some('\u{2026}'), Some('\u{2020}'),
+    Some('\u{2021}'), Some('\u{02C6}'), Some('\u{2030}'), Some('\u{0160}'), Some('\u{2039}'), Some('\u{0152}'), None,
+    Some('\u{017D}'), None, None, Some('\u{2018}'), Some('\u{2019}'), Some('\u{201C}'), Some('\u{201D}'),
+    Some('\u{2022}'), Some('\u{2013}'), Some('\u{2014}'), Some('\u{02DC}'), Some('\u{2122}'), Some('\u{0161}'),
+    Some('\u{203A}'), Some('\u{0153}'), None, Some('\u{017E}'), Some('\u{0178}'),
+];
+
+/// MacRomanEncoding 0x80..=0xFF
+const MAC_ROMAN_HIGH: &str = "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü†°¢£§•¶ß®©™´¨≠ÆØ∞±≤≥¥µ∂∑∏π∫ªºΩæø¿¡¬√ƒ≈∆«»…\u{A0}ÀÃÕŒœ–—“”‘’÷◊ÿŸ⁄€‹›ﬁﬂ‡·‚„‰ÂÊÁËÈÍÎÏÌÓÔ\u{F8FF}ÒÚÛÙıˆ˜¯˘˙˚¸˝˛ˇ";
+
+/// StandardEncoding 0xA1..=0xFF by glyph name, unused codes left empty
+const STANDARD_HIGH: [&str; 95] = [
+    "exclamdown", "cent", "sterling", "fraction", "yen", "florin", "section", "currency", "quotesingle",
+    "quotedblleft", "guillemotleft", "guilsinglleft", "guilsinglright", "fi", "fl", "", "endash", "dagger",
+    "daggerdbl", "periodcentered", "", "paragraph", "bullet", "quotesinglbase", "quotedblbase", "quotedblright",
+    "guillemotright", "ellipsis", "perthousand", "", "questiondown", "", "grave", "acute", "circumflex", "tilde",
+    "macron", "breve", "dotaccent", "dieresis", "", "ring", "cedilla", "", "hungarumlaut", "ogonek", "caron",
+    "emdash", "", "", "", "", "", "", "", "", "", "", "", "", "", "", "", "", "AE", "", "ordfeminine", "", "", "", "",
+    "Lslash", "Oslash", "OE", "ordmasculine", "", "", "", "", "", "ae", "", "", "", "dotlessi", "", "", "lslash",
+    "oslash", "oe", "germandbls", "", "", "", "",
+];
+
+/// the Unicode character of a glyph name: the names of the standard encodings, `uniXXXX` and `uXXXX`. suffixes such
+/// as `.sc` or `.alt` are dropped
+pub fn glyph_to_char(name: &[u8]) -> Option<char> {
+    let name = std::str::from_utf8(name).ok()?;
+    let name = name.split('.').next().unwrap_or(name);
+    if let Some(index) = ASCII_NAMES.iter().position(|known| *known == name) {
+        // 0x27 and 0x60 are named quoteright and quoteleft in StandardEncoding
+        return match name {
+            "quoteright" => Some('\u{2019}'),
+            "quoteleft" => Some('\u{2018}'),
+            _ => Some((0x20 + index as u8) as char),
+        };
+    }
+    if let Some(index) = LATIN1_NAMES.iter().position(|known| *known == name) {
+        return char::from_u32(0xA0 + index as u32);
+    }
+    if let Some((_, char)) = OTHER_NAMES.iter().find(|(known, _)| *known == name) {
+        return Some(*char);
+    }
+    let hex = name.strip_prefix("uni").filter(|hex| hex.len() == 4)
+        .or_else(|| name.strip_prefix('u').filter(|hex| (4..=6).contains(&hex.len())))?;
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+/// the 256 characters of a named simple font encoding
+pub fn named_encoding(name: &[u8]) -> Option<[Option<char>; 256]> {
+    let mut table = [None; 256];
+    match name {
+        b"WinAnsiEncoding" => {
+            for (code, slot) in table.iter_mut().enumerate().take(0x7F).skip(0x20) {
+                *slot = Some(code as u8 as char);
+            }
+            table[0x80..0xA0].copy_from_slice(&WIN_ANSI_HIGH);
+            for (code, slot) in table.iter_mut().enumerate().skip(0xA0) {
+                *slot = char::from_u32(code as u32);
+            }
+        }
+        b"MacRomanEncoding" => {
+            for (code, slot) in table.iter_mut().enumerate().take(0x7F).skip(0x20) {
+                *slot = Some(code as u8 as char);
+            }
+            for (code, char) in (0x80..=0xFF).zip(MAC_ROMAN_HIGH.chars()) {
+                table[code] = Some(char);
+            }
+        }
+        b"StandardEncoding" => {
+            for (code, name) in (0x20..=0x7E).zip(ASCII_NAMES) {
+                table[code] = glyph_to_char(name.as_bytes());
+            }
+            for (code, name) in (0xA1..=0xFF).zip(STANDARD_HIGH) {
+                table[code] = glyph_to_char(name.as_bytes());
+            }
+        }
+        _ => return None,
+    }
+    Some(table)
+}
+
+#[test]
+fn encoding_test() {
+    assert_eq!(MAC_ROMAN_HIGH.chars().count(), 128);
+
+    assert_eq!(glyph_to_char(b"A"), Some('A'));
+    assert_eq!(glyph_to_char(b"eacute"), Some('é'));
+    assert_eq!(glyph_to_char(b"quoteright"), Some('’'));
+    assert_eq!(glyph_to_char(b"uni20AC"), Some('€'));
+    assert_eq!(glyph_to_char(b"u1F600"), Some('😀'));
+    assert_eq!(glyph_to_char(b"a.sc"), Some('a'));
+    assert_eq!(glyph_to_char(b"g123"), None);
+
+    let win = named_encoding(b"WinAnsiEncoding").unwrap();
+    assert_eq!((win[b'A' as usize], win[0x80], win[0x81], win[0xE9]), (Some('A'), Some('€'), None, Some('é')));
+    let mac = named_encoding(b"MacRomanEncoding").unwrap();
+    assert_eq!((mac[0x8E], mac[0xD5]), (Some('é'), Some('’')));
+    let standard = named_encoding(b"StandardEncoding").unwrap();
+    assert_eq!((standard[0x27], standard[0xAE], standard[0xE1], standard[0xB0]), (Some('’'), Some('ﬁ'), Some('Æ'), None));
+    assert!(named_encoding(b"Custom").is_none());
+}
\ No newline at end of file
@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::pdf::object::ObjectId;
+
+#[derive(Debug)]
+pub enum PdfError {
+    Io(io::Error),
+    /// the bytes at position do not follow the PDF syntax
+    Syntax { position: u64, message: String },
+    /// a stream uses a filter that cannot be decoded
+    UnsupportedFilter(String),
+    /// the data of a stream is broken for one of its filters
+    Filter { filter: String, message: String },
+    /// resolving the object led back to itself
+    ReferenceCycle(ObjectId),
+    /// the objects parse but do not form the expected structure
+    Malformed(String),
+}
+
+impl PdfError {
+    pub fn syntax(position: u64, message: impl Into<String>) -> PdfError {
+        PdfError::Syntax { position, message: message.into() }
+    }
+}
+
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdfError::Io(error) => write!(f, "read error: {}", error),
+            PdfError::Syntax { position, message } => write!(f, "syntax error at {}: {}", position, message),
+            PdfError::UnsupportedFilter(filter) => write!(f, "unsupported stream filter {}", filter),
+            PdfError::Filter { filter, message } => write!(f, "{} failed: {}", filter, message),
+            PdfError::ReferenceCycle(id) => write!(f, "reference cycle through object {} {}", id.number, id.generation),
+            PdfError::Malformed(message) => write!(f, "malformed document: {}", message),
+        }
+    }
+}
+
+impl Error for PdfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PdfError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PdfError {
+    fn from(error: io::Error) -> PdfError {
+        PdfError::Io(error)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use flate2::{Decompress, FlushDecompress, Status};
+
+use crate::example::random_access_source::RandomAccessSource;
+use crate::example::window_random_access_source::WindowRandomAccessSource;
+use crate::pdf::error::PdfError;
+use crate::pdf::lexer::is_whitespace;
+use crate::pdf::object::{Dictionary, PdfObject, PdfStream};
+
+/// largest output a Flate, LZW or RunLength stream may decode to, so a tiny stream cannot expand without bound
+const MAX_DECODED_LENGTH: u64 = 256 * 1024 * 1024;
+const ENDSTREAM: &[u8] = b"endstream";
+
+/// One entry of a `/Filter` array.
+pub trait StreamFilter {
+    /// decode data, params being the `/DecodeParms` dictionary given for this filter
+    fn decode(&self, data: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>, PdfError>;
+}
+
+/// Filters by name, applied in the order a stream lists them.
+pub struct FilterRegistry {
+    filters: HashMap<Vec<u8>, Box<dyn StreamFilter>>,
+}
+
+impl FilterRegistry {
+    /// a registry with every standard filter that does not decode images, under its full and abbreviated names
+    pub fn new() -> FilterRegistry {
+        let mut registry = FilterRegistry::empty();
+        registry.register("FlateDecode", FlateDecode);
+        registry.register("Fl", FlateDecode);
+        registry.register("LZWDecode", LzwDecode);
+        registry.register("LZW", LzwDecode);
+        registry.register("ASCIIHexDecode", AsciiHexDecode);
+        registry.register("AHx", AsciiHexDecode);
+        registry.register("ASCII85Decode", Ascii85Decode);
+        registry.register("A85", Ascii85Decode);
+        registry.register("RunLengthDecode", RunLengthDecode);
+        registry.register("RL", RunLengthDecode);
+        registry
+    }
+
+    pub fn empty() -> FilterRegistry {
+        FilterRegistry { filters: HashMap::new() }
+    }
+
+    /// add a filter, replacing the one registered under the same name
+    pub fn register(&mut self, name: &str, filter: impl StreamFilter + 'static) {
+        self.filters.insert(name.as_bytes().to_vec(), Box::new(filter));
+    }
+
+    /// decode data through every filter of the stream dictionary. `/Filter` and `/DecodeParms` must be direct
+    pub fn decode(&self, dict: &Dictionary, data: Vec<u8>) -> Result<Vec<u8>, PdfError> {
+        let names = match dict.get("Filter") {
+            None => Vec::new(),
+            Some(PdfObject::Name(name)) => vec![&name[..]],
+            Some(PdfObject::Array(names)) => names.iter().map(PdfObject::as_name).collect::<Option<Vec<_>>>()
+                .ok_or_else(|| PdfError::Malformed("/Filter must hold names".to_string()))?,
+            Some(other) => return Err(PdfError::Malformed(format!("/Filter must be a name or an array, not {:?}", other))),
+        };
+        let params: Vec<Option<&Dictionary>> = match dict.get("DecodeParms") {
+            Some(PdfObject::Array(params)) => params.iter().map(PdfObject::as_dict).collect(),
+            Some(PdfObject::Dictionary(params)) => vec![Some(params)],
+            _ => Vec::new(),
+        };
+
+        let mut data = data;
+        for (index, name) in names.into_iter().enumerate() {
+            let filter = self.filters.get(name)
+                .ok_or_else(|| PdfError::UnsupportedFilter(String::from_utf8_lossy(name).into_owned()))?;
+            data = filter.decode(&data, params.get(index).copied().flatten())?;
+        }
+        Ok(data)
+    }
+}
+
+impl Default for FilterRegistry {
+    fn default() -> FilterRegistry {
+        FilterRegistry::new()
+    }
+}
+
+/// the decoded data of stream, whose raw data is length bytes read through a window of the source
+pub fn read_stream<S: RandomAccessSource>(source: &S, stream: &PdfStream, length: u64, filters: &FilterRegistry)
+                                          -> Result<Vec<u8>, PdfError> {
+    let length = stream_length(source, stream.data_offset, length)?;
+    let window = WindowRandomAccessSource::new(source, stream.data_offset, length)?;
+    let mut data = vec![0u8; length as usize];
+    window.read_fully_at(0, &mut data)?;
+    filters.decode(&stream.dict, data)
+}
+
+/// length when `endstream` follows that many bytes of data, otherwise the bytes up to the first `endstream`,
+/// since writers getting /Length wrong is common
+fn stream_length<S: RandomAccessSource>(source: &S, offset: u64, length: u64) -> Result<u64, PdfError> {
+    if ends_stream(source, offset.saturating_add(length))? {
+        return Ok(length);
+    }
+    let mut buffer = vec![0u8; 4096];
+    let mut position = offset;
+    loop {
+        let count = source.get_by_bytes(position, &mut buffer)?;
+        if let Some(index) = buffer[..count].windows(ENDSTREAM.len()).position(|window| window == ENDSTREAM) {
+            // the end of line before endstream is not part of the data
+            let mut end = position + index as u64;
+            for eol in [b'\n', b'\r'] {
+                if end > offset && source.get(end - 1)? == Some(eol) {
+                    end -= 1;
+                }
+            }
+            return Ok(end - offset);
+        }
+        if count < buffer.len() {
+            return Err(PdfError::syntax(offset, "stream is not followed by endstream"));
+        }
+        // keep enough of this buffer to find the keyword across the boundary
+        position += (count - ENDSTREAM.len() + 1) as u64;
+    }
+}
+
+/// whether the `endstream` keyword, after optional whitespace, is at position
+fn ends_stream<S: RandomAccessSource>(source: &S, position: u64) -> Result<bool, PdfError> {
+    let mut bytes = [0u8; 16 + ENDSTREAM.len()];
+    let count = source.get_by_bytes(position, &mut bytes)?;
+    let start = bytes[..count].iter().position(|byte| !is_whitespace(*byte)).unwrap_or(count);
+    Ok(bytes[start..count].starts_with(ENDSTREAM))
+}
+
+fn filter_error(filter: &str, message: impl Into<String>) -> PdfError {
+    PdfError::Filter { filter: filter.to_string(), message: message.into() }
+}
+
+pub struct FlateDecode;
+
+impl StreamFilter for FlateDecode {
+    fn decode(&self, data: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+        unpredict(inflate(data, MAX_DECODED_LENGTH)?, params)
+    }
+}
+
+/// zlib data decoded up to limit bytes, as much of it as decodes when it is truncated or damaged
+fn inflate(data: &[u8], limit: u64) -> Result<Vec<u8>, PdfError> {
+    let mut decoder = Decompress::new(true);
+    let mut decoded = Vec::new();
+    while decoded.len() as u64 <= limit {
+        decoded.reserve(64 * 1024);
+        let (read, written) = (decoder.total_in(), decoder.total_out());
+        let input = &data[read as usize..];
+        match decoder.decompress_vec(input, &mut decoded, FlushDecompress::None) {
+            Ok(Status::StreamEnd) => break,
+            // truncated data ends without the end of stream, keep what it decoded to
+            Ok(_) if decoder.total_in() == read && decoder.total_out() == written => break,
+            Ok(_) => {}
+            // and so does damaged data, as long as something came out before the damage
+            Err(error) if decoded.is_empty() => return Err(filter_error("FlateDecode", error.to_string())),
+            Err(_) => break,
+        }
+    }
+    if decoded.len() as u64 > limit {
+        return Err(filter_error("FlateDecode", format!("decodes to more than {} bytes", limit)));
+    }
+    Ok(decoded)
+}
+
+pub struct LzwDecode;
+
+impl StreamFilter for LzwDecode {
+    fn decode(&self, data: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+        let early_change = params.and_then(|params| params.get("EarlyChange")).and_then(PdfObject::as_i64) != Some(0);
+        unpredict(lzw_decode(data, early_change, MAX_DECODED_LENGTH)?, params)
+    }
+}
+
+const LZW_CLEAR: usize = 256;
+const LZW_END: usize = 257;
+const LZW_MAX_CODES: usize = 4096;
+
+/// codes of 9 to 12 bits, most significant bit first, decoded up to limit bytes. with early change the width grows
+/// one code early
+fn lzw_decode(data: &[u8], early_change: bool, limit: u64) -> Result<Vec<u8>, PdfError> {
+    let initial = || -> Vec<Vec<u8>> {
+        let mut table: Vec<Vec<u8>> = (0..=255u8).map(|byte| vec![byte]).collect();
+        // placeholders for the clear and end codes
+        table.push(Vec::new());
+        table.push(Vec::new());
+        table
+    };
+    let mut table = initial();
+    let mut decoded = Vec::new();
+    let mut previous: Option<usize> = None;
+    let mut width = 9;
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    let mut bytes = data.iter();
+
+    loop {
+        while bits < width {
+            match bytes.next() {
+                Some(byte) => {
+                    buffer = buffer << 8 | *byte as u32;
+                    bits += 8;
+                }
+                // a missing end code is tolerated
+                None => return Ok(decoded),
+            }
+        }
+        let code = (buffer >> (bits - width)) as usize & ((1 << width) - 1);
+        bits -= width;
+
+        match code {
+            LZW_CLEAR => {
+                table = initial();
+                previous = None;
+                width = 9;
+                continue;
+            }
+            LZW_END => return Ok(decoded),
+            _ => {}
+        }
+        let entry = match (table.get(code), previous) {
+            (Some(entry), _) => entry.clone(),
+            // the code being defined by this very step: previous entry plus its own first byte
+            (None, Some(previous)) if code == table.len() => {
+                let mut entry = table[previous].clone();
+                entry.push(entry[0]);
+                entry
+            }
+            _ => return Err(filter_error("LZWDecode", format!("code {} is not in the table", code))),
+        };
+        decoded.extend_from_slice(&entry);
+        if decoded.len() as u64 > limit {
+            return Err(filter_error("LZWDecode", format!("decodes to more than {} bytes", limit)));
+        }
+        if let Some(previous) = previous {
+            if table.len() < LZW_MAX_CODES {
+                let mut added = table[previous].clone();
+                added.push(entry[0]);
+                table.push(added);
+            }
+        }
+        previous = Some(code);
+
+        let next = table.len() + early_change as usize;
+        width = match next {
+            0..=511 => 9,
+            512..=1023 => 10,
+            1024..=2047 => 11,
+            _ => 12,
+        };
+    }
+}
+
+pub struct AsciiHexDecode;
+
+impl StreamFilter for AsciiHexDecode {
+    fn decode(&self, data: &[u8], _: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+        let mut decoded = Vec::with_capacity(data.len() / 2);
+        let mut high: Option<u8> = None;
+        for byte in data {
+            let digit = match byte {
+                b'>' => break,
+                byte if byte.is_ascii_whitespace() || *byte == 0 => continue,
+                byte => (*byte as char).to_digit(16)
+                    .ok_or_else(|| filter_error("ASCIIHexDecode", format!("{:?} is not a hex digit", *byte as char)))? as u8,
+            };
+            match high.take() {
+                Some(high) => decoded.push(high << 4 | digit),
+                None => high = Some(digit),
+            }
+        }
+        // an odd final digit is followed by an implied 0
+        if let Some(high) = high {
+            decoded.push(high << 4);
+        }
+        Ok(decoded)
+    }
+}
+
+pub struct Ascii85Decode;
+
+impl StreamFilter for Ascii85Decode {
+    fn decode(&self, data: &[u8], _: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+        let data = data.strip_prefix(b"<~").unwrap_or(data);
+        let mut decoded = Vec::with_capacity(data.len() / 5 * 4);
+        let mut group = [0u8; 5];
+        let mut filled = 0;
+        for byte in data {
+            match byte {
+                b'~' => break,
+                b'z' if filled == 0 => decoded.extend_from_slice(&[0; 4]),
+                b'!'..=b'u' => {
+                    group[filled] = byte - b'!';
+                    filled += 1;
+                    if filled == 5 {
+                        decoded.extend_from_slice(&ascii85_group(&group)?);
+                        filled = 0;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() || *byte == 0 => {}
+                byte => return Err(filter_error("ASCII85Decode", format!("{:?} is not a base-85 digit", *byte as char))),
+            }
+        }
+        // a final group of n digits is padded with the highest digit and gives n - 1 bytes
+        match filled {
+            0 => {}
+            1 => return Err(filter_error("ASCII85Decode", "final group has a single digit")),
+            _ => {
+                group[filled..].fill(84);
+                decoded.extend_from_slice(&ascii85_group(&group)?[..filled - 1]);
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+fn ascii85_group(digits: &[u8; 5]) -> Result<[u8; 4], PdfError> {
+    let value = digits.iter().fold(0u64, |value, digit| value * 85 + *digit as u64);
+    u32::try_from(value)
+        .map(u32::to_be_bytes)
+        .map_err(|_| filter_error("ASCII85Decode", "group is larger than 32 bits"))
+}
+
+pub struct RunLengthDecode;
+
+impl StreamFilter for RunLengthDecode {
+    fn decode(&self, data: &[u8], _: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+        run_length_decode(data, MAX_DECODED_LENGTH)
+    }
+}
+
+/// runs of literal or repeated bytes, decoded up to limit bytes
+fn run_length_decode(data: &[u8], limit: u64) -> Result<Vec<u8>, PdfError> {
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < data.len() {
+        let length = data[index] as usize;
+        index += 1;
+        match length {
+            0..=127 => {
+                let literal = data.get(index..index + length + 1)
+                    .ok_or_else(|| filter_error("RunLengthDecode", "literal run past the end of the data"))?;
+                decoded.extend_from_slice(literal);
+                index += length + 1;
+            }
+            128 => break,
+            _ => {
+                let byte = *data.get(index)
+                    .ok_or_else(|| filter_error("RunLengthDecode", "repeated run past the end of the data"))?;
+                decoded.resize(decoded.len() + 257 - length, byte);
+                index += 1;
+            }
+        }
+        if decoded.len() as u64 > limit {
+            return Err(filter_error("RunLengthDecode", format!("decodes to more than {} bytes", limit)));
+        }
+    }
+    Ok(decoded)
+}
+
+/// undo the `/Predictor` of Flate and LZW data: 2 for TIFF, 10 to 15 for PNG
+fn unpredict(data: Vec<u8>, params: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+    let param = |key: &str, default: i64| params.and_then(|params| params.get(key)).and_then(PdfObject::as_i64).unwrap_or(default);
+    let predictor = param("Predictor", 1);
+    if predictor == 1 {
+        return Ok(data);
+    }
+    let colors = param("Colors", 1);
+    let bits = param("BitsPerComponent", 8);
+    let columns = param("Columns", 1);
+    if !(1..=32).contains(&colors) || ![1, 2, 4, 8, 16].contains(&bits) || columns < 1 {
+        return Err(filter_error("Predictor", format!("unsupported Colors {} BitsPerComponent {} Columns {}", colors, bits, columns)));
+    }
+    let (colors, bits) = (colors as usize, bits as usize);
+    // Columns comes from the file, so a row of it may not even fit in memory
+    let row_length = usize::try_from(columns).ok()
+        .and_then(|columns| (colors * bits).checked_mul(columns))
+        .map(|row_bits| row_bits.div_ceil(8))
+        .ok_or_else(|| filter_error("Predictor", format!("Columns {} is too large", columns)))?;
+    // bytes per pixel, at least one, which the PNG filters look back by
+    let pixel_length = (colors * bits).div_ceil(8);
+
+    match predictor {
+        2 => Ok(tiff_unpredict(data, row_length, colors, bits)),
+        10..=15 => png_unpredict(&data, row_length, pixel_length),
+        _ => Err(filter_error("Predictor", format!("unknown predictor {}", predictor))),
+    }
+}
+
+fn tiff_unpredict(mut data: Vec<u8>, row_length: usize, colors: usize, bits: usize) -> Vec<u8> {
+    for row in data.chunks_mut(row_length) {
+        match bits {
+            8 => for index in colors..row.len() {
+                row[index] = row[index].wrapping_add(row[index - colors]);
+            },
+            16 => for index in (colors * 2..row.len() - 1).step_by(2) {
+                let left = u16::from_be_bytes([row[index - colors * 2], row[index - colors * 2 + 1]]);
+                let value = u16::from_be_bytes([row[index], row[index + 1]]).wrapping_add(left);
+                row[index..index + 2].copy_from_slice(&value.to_be_bytes());
+            },
+            _ => {
+                // components smaller than a byte, packed most significant first
+                let mask = (1u8 << bits) - 1;
+                let get = |row: &[u8], component: usize| {
+                    let bit = component * bits;
+                    row[bit / 8] >> (8 - bits - bit % 8) & mask
+                };
+                for component in colors..row.len() * 8 / bits {
+                    let value = get(row, component).wrapping_add(get(row, component - colors)) & mask;
+                    let bit = component * bits;
+                    let shift = 8 - bits - bit % 8;
+                    row[bit / 8] = row[bit / 8] & !(mask << shift) | value << shift;
+                }
+            }
+        }
+    }
+    data
+}
+
+/// every row starts with its own PNG filter type, so the predictor number itself does not matter
+fn png_unpredict(data: &[u8], row_length: usize, pixel_length: usize) -> Result<Vec<u8>, PdfError> {
+    let mut decoded = Vec::with_capacity(data.len());
+    // no row is longer than the data, whatever row_length says
+    let mut previous = vec![0u8; row_length.min(data.len())];
+    for chunk in data.chunks(row_length.saturating_add(1)) {
+        let (kind, row) = (chunk[0], &chunk[1..]);
+        let mut current = vec![0u8; row.len()];
+        for index in 0..row.len() {
+            let left = if index >= pixel_length { current[index - pixel_length] } else { 0 };
+            let up = previous[index];
+            let up_left = if index >= pixel_length { previous[index - pixel_length] } else { 0 };
+            let prediction = match kind {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(filter_error("Predictor", format!("unknown PNG filter type {}", kind))),
+            };
+            current[index] = row[index].wrapping_add(prediction);
+        }
+        decoded.extend_from_slice(&current);
+        previous[..current.len()].copy_from_slice(&current);
+    }
+    Ok(decoded)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) =
+        ((estimate - left as i16).abs(), (estimate - up as i16).abs(), (estimate - up_left as i16).abs());
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn deflate(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    use flate2::write::ZlibEncoder;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg(test)]
+fn params(entries: &[(&str, i64)]) -> Dictionary {
+    let mut params = Dictionary::new();
+    for (key, value) in entries {
+        params.insert(key, PdfObject::Integer(*value));
+    }
+    params
+}
+
+#[test]
+fn ascii_filters_test() {
+    assert_eq!(AsciiHexDecode.decode(b"48 65 6c\n6C 6F>ignored", None).unwrap(), b"Hello");
+    assert_eq!(AsciiHexDecode.decode(b"4 8 6>", None).unwrap(), [0x48, 0x60]);
+    assert!(AsciiHexDecode.decode(b"4g>", None).is_err());
+
+    assert_eq!(Ascii85Decode.decode(b"<~9jqo^zD\nef~>", None).unwrap(), b"Man \0\0\0\0ok");
+    assert_eq!(Ascii85Decode.decode(b"9jqo^", None).unwrap(), b"Man ");
+    assert!(Ascii85Decode.decode(b"9~>", None).is_err());
+    assert!(Ascii85Decode.decode(b"uuuuu~>", None).is_err());
+
+    assert_eq!(RunLengthDecode.decode(&[2, b'a', b'b', b'c', 254, b'x', 128, 0, b'!'], None).unwrap(), b"abcxxx");
+    assert!(RunLengthDecode.decode(&[5, b'a'], None).is_err());
+    // every two bytes repeat one 128 times
+    let runs = [129, b'x'].repeat(10);
+    assert_eq!(run_length_decode(&runs, 1280).unwrap().len(), 1280);
+    assert!(matches!(run_length_decode(&runs, 1279), Err(PdfError::Filter { .. })));
+}
+
+#[test]
+fn lzw_test() {
+    // the example from the PDF specification
+    let encoded = [0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01];
+    assert_eq!(LzwDecode.decode(&encoded, None).unwrap(), b"-----A---B");
+    assert_eq!(lzw_decode(&encoded, false, MAX_DECODED_LENGTH).unwrap(), b"-----A---B");
+    assert!(lzw_decode(&[0xFF, 0x80], true, MAX_DECODED_LENGTH).is_err());
+
+    // `a` then every code just being defined, each one byte longer than the one before: 1 + 2 + ... + 40 bytes
+    let codes = std::iter::once(b'a' as u32).chain(258..297);
+    let mut expanding = Vec::new();
+    let (mut buffer, mut bits) = (0u64, 0);
+    for code in codes {
+        buffer = buffer << 9 | code as u64;
+        bits += 9;
+        while bits >= 8 {
+            bits -= 8;
+            expanding.push((buffer >> bits) as u8);
+        }
+    }
+    assert_eq!(lzw_decode(&expanding, true, 820).unwrap(), vec![b'a'; 820]);
+    assert!(matches!(lzw_decode(&expanding, true, 819), Err(PdfError::Filter { .. })));
+}
+
+#[test]
+fn flate_predictor_test() {
+    let text = b"a longer line of text, a longer line of text, a longer line of text".repeat(10);
+    assert_eq!(FlateDecode.decode(&deflate(&text), None).unwrap(), text);
+    assert!(FlateDecode.decode(b"not zlib", None).is_err());
+    let numbers: Vec<u8> = (0..2000).map(|number| format!("{} ", number)).collect::<String>().into_bytes();
+    let compressed = deflate(&numbers);
+    let partial = FlateDecode.decode(&compressed[..compressed.len() / 2], None).unwrap();
+    assert!(!partial.is_empty() && numbers.starts_with(&partial));
+    let bomb = deflate(&[0u8; 100_001]);
+    assert_eq!(inflate(&bomb, 100_001).unwrap().len(), 100_001);
+    assert!(matches!(inflate(&bomb, 100_000), Err(PdfError::Filter { .. })));
+
+    // PNG rows of 3 one byte pixels: None, Sub, Up, Average and Paeth
+    let rows = [0, 1, 2, 3, 1, 1, 1, 1, 2, 1, 1, 1, 3, 2, 2, 2, 4, 1, 0, 0];
+    let png = params(&[("Predictor", 12), ("Columns", 3)]);
+    assert_eq!(FlateDecode.decode(&deflate(&rows), Some(&png)).unwrap(),
+               [1, 2, 3, 1, 2, 3, 2, 3, 4, 3, 5, 6, 4, 5, 6]);
+
+    // TIFF with two colors of 8 and 4 bits
+    let tiff = params(&[("Predictor", 2), ("Colors", 2), ("Columns", 3)]);
+    assert_eq!(unpredict(vec![1, 10, 1, 1, 2, 2, 5, 5, 0, 0, 0, 0], Some(&tiff)).unwrap(),
+               [1, 10, 2, 11, 4, 13, 5, 5, 5, 5, 5, 5]);
+    let nibbles = params(&[("Predictor", 2), ("Colors", 1), ("Columns", 4), ("BitsPerComponent", 4)]);
+    assert_eq!(unpredict(vec![0x31, 0xF2], Some(&nibbles)).unwrap(), [0x34, 0x35]);
+    assert!(unpredict(vec![0; 4], Some(&params(&[("Predictor", 3)]))).is_err());
+    for predictor in [2, 12] {
+        let huge = params(&[("Predictor", predictor), ("Colors", 32), ("BitsPerComponent", 16), ("Columns", i64::MAX)]);
+        assert!(matches!(unpredict(vec![0; 4], Some(&huge)), Err(PdfError::Filter { .. })));
+    }
+    let wide = params(&[("Predictor", 12), ("Columns", 1 << 40)]);
+    assert_eq!(unpredict(vec![0, 1, 2], Some(&wide)).unwrap(), [1, 2]);
+}
+
+#[test]
+fn read_stream_length_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let source = ArrayRandomAccessSource::new(b"<< >>\nstream\nsome data\r\nendstream\nendobj".to_vec());
+    let stream = PdfStream { dict: Dictionary::new(), data_offset: 13 };
+    let filters = FilterRegistry::new();
+    assert_eq!(read_stream(&source, &stream, 9, &filters).unwrap(), b"some data");
+    // a /Length too short, too long or past the end falls back to where endstream is
+    for length in [4, 14, 1000] {
+        assert_eq!(read_stream(&source, &stream, length, &filters).unwrap(), b"some data");
+    }
+    let unterminated = ArrayRandomAccessSource::new(b"<< >>\nstream\nsome data".to_vec());
+    assert!(read_stream(&unterminated, &stream, 4, &filters).is_err());
+}
+
+#[test]
+fn registry_test() {
+    struct Reverse;
+
+    impl StreamFilter for Reverse {
+        fn decode(&self, data: &[u8], _: Option<&Dictionary>) -> Result<Vec<u8>, PdfError> {
+            Ok(data.iter().rev().copied().collect())
+        }
+    }
+
+    let hex: Vec<u8> = deflate(b"chained").iter().map(|byte| format!("{:02x}", byte)).collect::<String>().into_bytes();
+    let mut dict = Dictionary::new();
+    dict.insert("Filter", PdfObject::Array(vec![PdfObject::Name(b"AHx".to_vec()), PdfObject::Name(b"FlateDecode".to_vec())]));
+    dict.insert("DecodeParms", PdfObject::Array(vec![PdfObject::Null, PdfObject::Dictionary(params(&[("Predictor", 1)]))]));
+    let mut registry = FilterRegistry::new();
+    assert_eq!(registry.decode(&dict, hex.clone()).unwrap(), b"chained");
+
+    dict.insert("Filter", PdfObject::Array(vec![PdfObject::Name(b"Reverse".to_vec())]));
+    assert!(matches!(registry.decode(&dict, hex), Err(PdfError::UnsupportedFilter(name)) if name == "Reverse"));
+    registry.register("Reverse", Reverse);
+    assert_eq!(registry.decode(&dict, b"olleh".to_vec()).unwrap(), b"hello");
+    assert_eq!(FilterRegistry::empty().decode(&Dictionary::new(), b"raw".to_vec()).unwrap(), b"raw");
+}
\ No newline at end of file
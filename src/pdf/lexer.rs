@@ -0,0 +1,372 @@
+use std::io::{BufRead, Seek, SeekFrom};
+
+use crate::example::random_access_reader::RandomAccessReader;
+use crate::example::random_access_source::RandomAccessSource;
+use crate::pdf::error::PdfError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Integer(i64),
+    Real(f64),
+    /// a name without its leading `/`, `#xx` escapes decoded
+    Name(Vec<u8>),
+    /// a `(...)` string, escapes decoded
+    LiteralString(Vec<u8>),
+    /// a `<...>` string, decoded to its bytes
+    HexString(Vec<u8>),
+    /// any other run of regular characters: `true`, `obj`, `R`, `Tj`...
+    Keyword(Vec<u8>),
+    /// a comment without its leading `%`
+    Comment(Vec<u8>),
+    ArrayStart,
+    ArrayEnd,
+    DictStart,
+    DictEnd,
+}
+
+/// white-space characters of PDF 32000-1 table 1
+pub fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+/// delimiter characters of PDF 32000-1 table 2
+pub fn is_delimiter(byte: u8) -> bool {
+    matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn is_regular(byte: u8) -> bool {
+    !is_whitespace(byte) && !is_delimiter(byte)
+}
+
+/// Splits the bytes of a `RandomAccessSource` into PDF tokens, each with the offset it starts at.
+pub struct Lexer<S: RandomAccessSource> {
+    reader: RandomAccessReader<S>,
+}
+
+impl<S: RandomAccessSource> Lexer<S> {
+    pub fn new(source: S) -> Lexer<S> {
+        Lexer { reader: RandomAccessReader::new(source) }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.reader.position()
+    }
+
+    pub fn seek(&mut self, position: u64) -> Result<(), PdfError> {
+        self.reader.seek(SeekFrom::Start(position))?;
+        Ok(())
+    }
+
+    pub fn source(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    pub fn peek_byte(&mut self) -> Result<Option<u8>, PdfError> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    pub fn next_byte(&mut self) -> Result<Option<u8>, PdfError> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    pub fn skip_whitespace(&mut self) -> Result<(), PdfError> {
+        while let Some(byte) = self.peek_byte()? {
+            if !is_whitespace(byte) {
+                break;
+            }
+            self.reader.consume(1);
+        }
+        Ok(())
+    }
+
+    /// the next token and its offset, `None` at the end of the source
+    pub fn next_token(&mut self) -> Result<Option<(u64, Token)>, PdfError> {
+        self.skip_whitespace()?;
+        let position = self.offset();
+        let byte = match self.next_byte()? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+        let token = match byte {
+            b'%' => Token::Comment(self.read_comment()?),
+            b'/' => Token::Name(self.read_name(position)?),
+            b'(' => Token::LiteralString(self.read_literal_string(position)?),
+            b'[' => Token::ArrayStart,
+            b']' => Token::ArrayEnd,
+            b'<' if self.peek_byte()? == Some(b'<') => {
+                self.reader.consume(1);
+                Token::DictStart
+            }
+            b'<' => Token::HexString(self.read_hex_string(position)?),
+            b'>' if self.peek_byte()? == Some(b'>') => {
+                self.reader.consume(1);
+                Token::DictEnd
+            }
+            b'{' | b'}' => Token::Keyword(vec![byte]),
+            b')' | b'>' => return Err(PdfError::syntax(position, format!("unexpected '{}'", byte as char))),
+            _ => {
+                let mut word = vec![byte];
+                self.read_regular(&mut word)?;
+                number(&word).unwrap_or(Token::Keyword(word))
+            }
+        };
+        Ok(Some((position, token)))
+    }
+
+    fn read_regular(&mut self, word: &mut Vec<u8>) -> Result<(), PdfError> {
+        while let Some(byte) = self.peek_byte()? {
+            if !is_regular(byte) {
+                break;
+            }
+            word.push(byte);
+            self.reader.consume(1);
+        }
+        Ok(())
+    }
+
+    fn read_comment(&mut self) -> Result<Vec<u8>, PdfError> {
+        let mut comment = Vec::new();
+        while let Some(byte) = self.peek_byte()? {
+            if byte == b'\r' || byte == b'\n' {
+                break;
+            }
+            comment.push(byte);
+            self.reader.consume(1);
+        }
+        Ok(comment)
+    }
+
+    fn read_name(&mut self, position: u64) -> Result<Vec<u8>, PdfError> {
+        let mut raw = Vec::new();
+        self.read_regular(&mut raw)?;
+        let mut name = Vec::with_capacity(raw.len());
+        let mut index = 0;
+        while index < raw.len() {
+            if raw[index] == b'#' {
+                let code = raw.get(index + 1..index + 3)
+                    .and_then(|hex| Some(hex_value(hex[0])? << 4 | hex_value(hex[1])?))
+                    .ok_or_else(|| PdfError::syntax(position, "invalid #xx escape in name"))?;
+                name.push(code);
+                index += 3;
+            } else {
+                name.push(raw[index]);
+                index += 1;
+            }
+        }
+        Ok(name)
+    }
+
+    fn read_literal_string(&mut self, position: u64) -> Result<Vec<u8>, PdfError> {
+        let mut string = Vec::new();
+        let mut depth = 1;
+        loop {
+            let byte = self.next_byte()?.ok_or_else(|| PdfError::syntax(position, "unterminated string"))?;
+            match byte {
+                b'(' => {
+                    depth += 1;
+                    string.push(byte);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(string);
+                    }
+                    string.push(byte);
+                }
+                // an end of line in a string is always read as a single line feed
+                b'\r' => {
+                    if self.peek_byte()? == Some(b'\n') {
+                        self.reader.consume(1);
+                    }
+                    string.push(b'\n');
+                }
+                b'\\' => self.read_escape(position, &mut string)?,
+                _ => string.push(byte),
+            }
+        }
+    }
+
+    fn read_escape(&mut self, position: u64, string: &mut Vec<u8>) -> Result<(), PdfError> {
+        let byte = self.next_byte()?.ok_or_else(|| PdfError::syntax(position, "unterminated string"))?;
+        match byte {
+            b'n' => string.push(b'\n'),
+            b'r' => string.push(b'\r'),
+            b't' => string.push(b'\t'),
+            b'b' => string.push(b'\x08'),
+            b'f' => string.push(b'\x0c'),
+            b'(' | b')' | b'\\' => string.push(byte),
+            // a backslash at the end of a line continues the string on the next one
+            b'\r' => {
+                if self.peek_byte()? == Some(b'\n') {
+                    self.reader.consume(1);
+                }
+            }
+            b'\n' => {}
+            b'0'..=b'7' => {
+                let mut code = (byte - b'0') as u32;
+                for _ in 0..2 {
+                    match self.peek_byte()? {
+                        Some(digit @ b'0'..=b'7') => {
+                            code = code * 8 + (digit - b'0') as u32;
+                            self.reader.consume(1);
+                        }
+                        _ => break,
+                    }
+                }
+                // high-order overflow of \ddd is ignored
+                string.push(code as u8);
+            }
+            // the backslash of an unknown escape is ignored
+            _ => string.push(byte),
+        }
+        Ok(())
+    }
+
+    fn read_hex_string(&mut self, position: u64) -> Result<Vec<u8>, PdfError> {
+        let mut string = Vec::new();
+        let mut high: Option<u8> = None;
+        loop {
+            let byte = self.next_byte()?.ok_or_else(|| PdfError::syntax(position, "unterminated hex string"))?;
+            if byte == b'>' {
+                break;
+            }
+            if is_whitespace(byte) {
+                continue;
+            }
+            let value = hex_value(byte).ok_or_else(|| PdfError::syntax(position, "invalid digit in hex string"))?;
+            match high.take() {
+                Some(high) => string.push(high << 4 | value),
+                None => high = Some(value),
+            }
+        }
+        // an odd number of digits reads as if followed by 0
+        if let Some(high) = high {
+            string.push(high << 4);
+        }
+        Ok(string)
+    }
+}
+
+impl<S: RandomAccessSource> Iterator for Lexer<S> {
+    type Item = Result<(u64, Token), PdfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().transpose()
+    }
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|value| value as u8)
+}
+
+fn number(word: &[u8]) -> Option<Token> {
+    let text = std::str::from_utf8(word).ok()?;
+    let digits = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit() || byte == b'.')
+        || digits.bytes().filter(|byte| *byte == b'.').count() > 1 || digits == "." {
+        return None;
+    }
+    if digits.contains('.') {
+        text.parse().ok().map(Token::Real)
+    } else {
+        // integers too big for i64 are still numbers
+        text.parse().map(Token::Integer).or_else(|_| text.parse().map(Token::Real)).ok()
+    }
+}
+
+#[cfg(test)]
+fn tokens(bytes: &'static [u8]) -> Vec<(u64, Token)> {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    Lexer::new(ArrayRandomAccessSource::new(bytes)).map(|token| token.unwrap()).collect()
+}
+
+#[test]
+fn lexer_tokens_test() {
+    let tokens = tokens(b"%PDF-1.7\n1 0 obj\n<< /Type /Page /Kids [3 0 R] /Ratio -.5 >>\nendobj true null {");
+    assert_eq!(tokens, vec![
+        (0, Token::Comment(b"PDF-1.7".to_vec())),
+        (9, Token::Integer(1)),
+        (11, Token::Integer(0)),
+        (13, Token::Keyword(b"obj".to_vec())),
+        (17, Token::DictStart),
+        (20, Token::Name(b"Type".to_vec())),
+        (26, Token::Name(b"Page".to_vec())),
+        (32, Token::Name(b"Kids".to_vec())),
+        (38, Token::ArrayStart),
+        (39, Token::Integer(3)),
+        (41, Token::Integer(0)),
+        (43, Token::Keyword(b"R".to_vec())),
+        (44, Token::ArrayEnd),
+        (46, Token::Name(b"Ratio".to_vec())),
+        (53, Token::Real(-0.5)),
+        (57, Token::DictEnd),
+        (60, Token::Keyword(b"endobj".to_vec())),
+        (67, Token::Keyword(b"true".to_vec())),
+        (72, Token::Keyword(b"null".to_vec())),
+        (77, Token::Keyword(b"{".to_vec())),
+    ]);
+}
+
+#[test]
+fn lexer_numbers_and_names_test() {
+    let tokens: Vec<Token> = tokens(b"+17 -98 4. 34.5 0.0 +.002 1e5 /A#20B /#41#42 /a;b/ /1.0 --1")
+        .into_iter().map(|(_, token)| token).collect();
+    assert_eq!(tokens, vec![
+        Token::Integer(17),
+        Token::Integer(-98),
+        Token::Real(4.0),
+        Token::Real(34.5),
+        Token::Real(0.0),
+        Token::Real(0.002),
+        Token::Keyword(b"1e5".to_vec()),
+        Token::Name(b"A B".to_vec()),
+        Token::Name(b"AB".to_vec()),
+        Token::Name(b"a;b".to_vec()),
+        Token::Name(Vec::new()),
+        Token::Name(b"1.0".to_vec()),
+        Token::Keyword(b"--1".to_vec()),
+    ]);
+}
+
+#[test]
+fn lexer_strings_test() {
+    let tokens: Vec<Token> = tokens(b"(a (nested) \\(paren\\)) (\\n\\r\\t\\b\\f\\\\\\101\\7\\0053\\q) \
+(line\\\r\ncontinued) (cr\r\nlf\rend) <48 65 6C6C 6F> <7> <>")
+        .into_iter().map(|(_, token)| token).collect();
+    assert_eq!(tokens, vec![
+        Token::LiteralString(b"a (nested) (paren)".to_vec()),
+        Token::LiteralString(b"\n\r\t\x08\x0c\\A\x07\x053q".to_vec()),
+        Token::LiteralString(b"linecontinued".to_vec()),
+        Token::LiteralString(b"cr\nlf\nend".to_vec()),
+        Token::HexString(b"Hello".to_vec()),
+        Token::HexString(vec![0x70]),
+        Token::HexString(Vec::new()),
+    ]);
+}
+
+#[test]
+fn lexer_whitespace_and_errors_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let tokens = tokens(b"\0\t\x0c\r\n 1%comment\r2");
+    assert_eq!(tokens, vec![
+        (6, Token::Integer(1)),
+        (7, Token::Comment(b"comment".to_vec())),
+        (16, Token::Integer(2)),
+    ]);
+
+    for bad in [&b"(open"[..], b"<4G>", b")", b"/A#4"] {
+        let mut lexer = Lexer::new(ArrayRandomAccessSource::new(bad));
+        assert!(matches!(lexer.next_token(), Err(PdfError::Syntax { position: 0, .. })), "{:?}", bad);
+    }
+
+    let mut lexer = Lexer::new(ArrayRandomAccessSource::new(&b"1 2 3"[..]));
+    lexer.seek(4).unwrap();
+    assert_eq!(lexer.next_token().unwrap(), Some((4, Token::Integer(3))));
+    assert_eq!(lexer.next_token().unwrap(), None);
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+pub mod document;
+pub mod encoding;
+pub mod error;
+pub mod filter;
+pub mod lexer;
+pub mod object;
+pub mod text;
+pub mod writer;
+pub mod xref;
\ No newline at end of file
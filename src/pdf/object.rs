@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::example::random_access_source::RandomAccessSource;
+use crate::pdf::error::PdfError;
+use crate::pdf::lexer::{Lexer, Token};
+
+/// deepest nesting of arrays and dictionaries parsed, so a hostile file cannot overflow the stack
+const MAX_NESTING: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId {
+    pub number: u32,
+    pub generation: u16,
+}
+
+impl ObjectId {
+    pub fn new(number: u32, generation: u16) -> ObjectId {
+        ObjectId { number, generation }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dictionary {
+    entries: BTreeMap<Vec<u8>, PdfObject>,
+}
+
+impl Dictionary {
+    pub fn new() -> Dictionary {
+        Dictionary::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PdfObject> {
+        self.entries.get(key.as_bytes())
+    }
+
+    pub fn insert(&mut self, key: &str, value: PdfObject) -> Option<PdfObject> {
+        self.entries.insert(key.as_bytes().to_vec(), value)
+    }
+
+    pub fn insert_raw(&mut self, key: Vec<u8>, value: PdfObject) -> Option<PdfObject> {
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<PdfObject> {
+        self.entries.remove(key.as_bytes())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &PdfObject)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// whether `/Type` is the name type
+    pub fn has_type(&self, type_name: &str) -> bool {
+        self.get("Type").and_then(PdfObject::as_name) == Some(type_name.as_bytes())
+    }
+}
+
+/// A stream as found in the file: its dictionary and the offset its data starts at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfStream {
+    pub dict: Dictionary,
+    pub data_offset: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PdfObject {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(f64),
+    Name(Vec<u8>),
+    String(Vec<u8>),
+    Array(Vec<PdfObject>),
+    Dictionary(Dictionary),
+    Stream(PdfStream),
+    Reference(ObjectId),
+}
+
+impl PdfObject {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            PdfObject::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// integers and reals as a float
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PdfObject::Integer(value) => Some(*value as f64),
+            PdfObject::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_name(&self) -> Option<&[u8]> {
+        match self {
+            PdfObject::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_string(&self) -> Option<&[u8]> {
+        match self {
+            PdfObject::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<PdfObject>> {
+        match self {
+            PdfObject::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// the dictionary of a dictionary or of a stream
+    pub fn as_dict(&self) -> Option<&Dictionary> {
+        match self {
+            PdfObject::Dictionary(dict) => Some(dict),
+            PdfObject::Stream(stream) => Some(&stream.dict),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream(&self) -> Option<&PdfStream> {
+        match self {
+            PdfObject::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    pub fn as_reference(&self) -> Option<ObjectId> {
+        match self {
+            PdfObject::Reference(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// Parses PDF objects from the tokens of a `Lexer`, resolving `n g R` into references.
+pub struct Parser<S: RandomAccessSource> {
+    lexer: Lexer<S>,
+    peeked: VecDeque<(u64, Token)>,
+    depth: usize,
+}
+
+impl<S: RandomAccessSource> Parser<S> {
+    pub fn new(source: S) -> Parser<S> {
+        Parser { lexer: Lexer::new(source), peeked: VecDeque::new(), depth: 0 }
+    }
+
+    pub fn seek(&mut self, position: u64) -> Result<(), PdfError> {
+        self.peeked.clear();
+        self.lexer.seek(position)
+    }
+
+    /// offset of the next token, or of the end of the last one when none has been peeked
+    pub fn offset(&self) -> u64 {
+        self.peeked.front().map_or(self.lexer.offset(), |(position, _)| *position)
+    }
+
+    pub fn source(&self) -> &S {
+        self.lexer.source()
+    }
+
+    /// the next token that is not a comment
+    pub fn next_token(&mut self) -> Result<Option<(u64, Token)>, PdfError> {
+        if let Some(token) = self.peeked.pop_front() {
+            return Ok(Some(token));
+        }
+        loop {
+            match self.lexer.next_token()? {
+                Some((_, Token::Comment(_))) => continue,
+                token => return Ok(token),
+            }
+        }
+    }
+
+    /// look at the token index places ahead without consuming it
+    pub fn peek_token(&mut self, index: usize) -> Result<Option<&Token>, PdfError> {
+        while self.peeked.len() <= index {
+            match self.lexer.next_token()? {
+                Some((_, Token::Comment(_))) => continue,
+                Some(token) => self.peeked.push_back(token),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.peeked.get(index).map(|(_, token)| token))
+    }
+
+    /// consume the next token, which must be the keyword
+    pub fn expect_keyword(&mut self, keyword: &str) -> Result<u64, PdfError> {
+        match self.next_token()? {
+            Some((position, Token::Keyword(word))) if word == keyword.as_bytes() => Ok(position),
+            Some((position, token)) => Err(PdfError::syntax(position, format!("expected {} but found {:?}", keyword, token))),
+            None => Err(PdfError::syntax(self.offset(), format!("expected {} but the source ended", keyword))),
+        }
+    }
+
+    pub fn parse_object(&mut self) -> Result<PdfObject, PdfError> {
+        let (position, token) = self.next_token()?
+            .ok_or_else(|| PdfError::syntax(self.offset(), "expected an object but the source ended"))?;
+        match token {
+            Token::Integer(value) => {
+                let is_reference = matches!(self.peek_token(0)?, Some(Token::Integer(_)))
+                    && matches!(self.peek_token(1)?, Some(Token::Keyword(word)) if word == b"R");
+                if !is_reference {
+                    return Ok(PdfObject::Integer(value));
+                }
+                let generation = self.next_token()?.and_then(|(_, token)| match token {
+                    Token::Integer(generation) => Some(generation),
+                    _ => None,
+                }).unwrap_or(0);
+                self.next_token()?;
+                reference(position, value, generation)
+            }
+            Token::Real(value) => Ok(PdfObject::Real(value)),
+            Token::Name(name) => Ok(PdfObject::Name(name)),
+            Token::LiteralString(string) | Token::HexString(string) => Ok(PdfObject::String(string)),
+            Token::ArrayStart => self.nested(position, |parser| {
+                let mut array = Vec::new();
+                loop {
+                    match parser.peek_token(0)? {
+                        Some(Token::ArrayEnd) => {
+                            parser.next_token()?;
+                            return Ok(PdfObject::Array(array));
+                        }
+                        Some(_) => array.push(parser.parse_object()?),
+                        None => return Err(PdfError::syntax(position, "unterminated array")),
+                    }
+                }
+            }),
+            Token::DictStart => Ok(PdfObject::Dictionary(self.parse_dictionary_entries(position)?)),
+            Token::Keyword(word) => match &word[..] {
+                b"true" => Ok(PdfObject::Boolean(true)),
+                b"false" => Ok(PdfObject::Boolean(false)),
+                b"null" => Ok(PdfObject::Null),
+                _ => Err(PdfError::syntax(position, format!("unexpected keyword {}", String::from_utf8_lossy(&word)))),
+            },
+            token => Err(PdfError::syntax(position, format!("unexpected {:?}", token))),
+        }
+    }
+
+    pub fn parse_dictionary(&mut self) -> Result<Dictionary, PdfError> {
+        match self.next_token()? {
+            Some((position, Token::DictStart)) => self.parse_dictionary_entries(position),
+            Some((position, token)) => Err(PdfError::syntax(position, format!("expected a dictionary but found {:?}", token))),
+            None => Err(PdfError::syntax(self.offset(), "expected a dictionary but the source ended")),
+        }
+    }
+
+    /// parse the inside of an array or dictionary starting at position, one level deeper
+    fn nested<T>(&mut self, position: u64, parse: impl FnOnce(&mut Self) -> Result<T, PdfError>) -> Result<T, PdfError> {
+        if self.depth >= MAX_NESTING {
+            return Err(PdfError::syntax(position, "arrays and dictionaries nested too deep"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_dictionary_entries(&mut self, position: u64) -> Result<Dictionary, PdfError> {
+        self.nested(position, |parser| parser.parse_entries(position))
+    }
+
+    fn parse_entries(&mut self, position: u64) -> Result<Dictionary, PdfError> {
+        let mut dict = Dictionary::new();
+        loop {
+            match self.next_token()? {
+                Some((_, Token::DictEnd)) => return Ok(dict),
+                Some((_, Token::Name(key))) => {
+                    let value = self.parse_object()?;
+                    // a null value is the same as a missing entry
+                    if value != PdfObject::Null {
+                        dict.insert_raw(key, value);
+                    }
+                }
+                Some((key_position, token)) =>
+                    return Err(PdfError::syntax(key_position, format!("expected a name key but found {:?}", token))),
+                None => return Err(PdfError::syntax(position, "unterminated dictionary")),
+            }
+        }
+    }
+
+    /// parse `n g obj ... endobj` at the current offset. a stream is returned with the offset of its data, which is
+    /// left unread, so `endobj` is only checked for other objects.
+    pub fn parse_indirect_object(&mut self) -> Result<(ObjectId, PdfObject), PdfError> {
+        let position = self.offset();
+        let number = match self.next_token()? {
+            Some((_, Token::Integer(number))) => number,
+            _ => return Err(PdfError::syntax(position, "expected an object number")),
+        };
+        let generation = match self.next_token()? {
+            Some((_, Token::Integer(generation))) => generation,
+            _ => return Err(PdfError::syntax(position, "expected a generation number")),
+        };
+        let id = match reference(position, number, generation)? {
+            PdfObject::Reference(id) => id,
+            _ => unreachable!(),
+        };
+        self.expect_keyword("obj")?;
+        let object = self.parse_object()?;
+
+        match (object, self.peek_token(0)?) {
+            (PdfObject::Dictionary(dict), Some(Token::Keyword(word))) if word == b"stream" => {
+                self.next_token()?;
+                // the stream keyword is followed by CRLF or LF, tolerate a lone CR too
+                if self.lexer.peek_byte()? == Some(b'\r') {
+                    self.lexer.next_byte()?;
+                }
+                if self.lexer.peek_byte()? == Some(b'\n') {
+                    self.lexer.next_byte()?;
+                }
+                Ok((id, PdfObject::Stream(PdfStream { dict, data_offset: self.lexer.offset() })))
+            }
+            (object, _) => {
+                self.expect_keyword("endobj")?;
+                Ok((id, object))
+            }
+        }
+    }
+}
+
+fn reference(position: u64, number: i64, generation: i64) -> Result<PdfObject, PdfError> {
+    match (u32::try_from(number), u16::try_from(generation)) {
+        (Ok(number), Ok(generation)) => Ok(PdfObject::Reference(ObjectId::new(number, generation))),
+        _ => Err(PdfError::syntax(position, "object number out of range")),
+    }
+}
+
+#[cfg(test)]
+fn parser(bytes: &'static [u8]) -> Parser<crate::example::array_random_access_source::ArrayRandomAccessSource<&'static [u8]>> {
+    Parser::new(crate::example::array_random_access_source::ArrayRandomAccessSource::new(bytes))
+}
+
+#[test]
+fn parse_object_test() {
+    let mut parser = parser(b"<< /Type /Page /Kids [1 0 R 2 3 4 R] /Empty null %note\n /Inner << /A (x) /B <41> >> \
+/Flag true /Size 1.5 >> 7");
+    let object = parser.parse_object().unwrap();
+    let dict = object.as_dict().unwrap();
+    assert!(dict.has_type("Page"));
+    assert_eq!(dict.get("Kids").unwrap().as_array().unwrap(), &vec![
+        PdfObject::Reference(ObjectId::new(1, 0)),
+        PdfObject::Integer(2),
+        PdfObject::Reference(ObjectId::new(3, 4)),
+    ]);
+    assert_eq!(dict.get("Empty"), None);
+    let inner = dict.get("Inner").unwrap().as_dict().unwrap();
+    assert_eq!(inner.get("A").unwrap().as_string(), Some(&b"x"[..]));
+    assert_eq!(inner.get("B").unwrap().as_string(), Some(&b"A"[..]));
+    assert_eq!(dict.get("Flag"), Some(&PdfObject::Boolean(true)));
+    assert_eq!(dict.get("Size").unwrap().as_f64(), Some(1.5));
+    assert_eq!(parser.parse_object().unwrap(), PdfObject::Integer(7));
+    assert!(parser.parse_object().is_err());
+
+    // deep nesting is an error instead of a stack overflow, and the depth is given back after each object
+    let deep: &'static [u8] = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000)).into_bytes().leak();
+    assert!(self::parser(deep).parse_object().is_err());
+    let nested: &'static [u8] = format!("{}{} <<>>", "[".repeat(200), "]".repeat(200)).into_bytes().leak();
+    let mut parser = self::parser(nested);
+    assert!(parser.parse_object().is_ok());
+    assert!(parser.parse_dictionary().is_ok());
+}
+
+#[test]
+fn parse_indirect_object_test() {
+    let mut parser = parser(b"12 0 obj\n[/A 1]\nendobj\n13 1 obj << /Length 5 >>\nstream\r\nhello\nendstream\nendobj");
+    assert_eq!(parser.parse_indirect_object().unwrap(), (ObjectId::new(12, 0), PdfObject::Array(vec![
+        PdfObject::Name(b"A".to_vec()),
+        PdfObject::Integer(1),
+    ])));
+    let (id, object) = parser.parse_indirect_object().unwrap();
+    assert_eq!(id, ObjectId::new(13, 1));
+    let stream = object.as_stream().unwrap();
+    assert_eq!(stream.dict.get("Length"), Some(&PdfObject::Integer(5)));
+    assert_eq!(stream.data_offset, 56);
+
+    assert!(matches!(parser.parse_indirect_object(), Err(PdfError::Syntax { .. })));
+    let mut broken = self::parser(b"1 0 obj 5 endstream");
+    assert!(broken.parse_indirect_object().is_err());
+}
\ No newline at end of file
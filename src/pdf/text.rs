@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::example::array_random_access_source::ArrayRandomAccessSource;
+use crate::example::random_access_source::RandomAccessSource;
+use crate::pdf::document::{Page, PdfDocument};
+use crate::pdf::encoding::{glyph_to_char, named_encoding};
+use crate::pdf::error::PdfError;
+use crate::pdf::lexer::{is_whitespace, Lexer, Token};
+use crate::pdf::object::{Dictionary, ObjectId, Parser, PdfObject};
+
+/// form XObjects drawing forms nest at most this deep, which also stops forms drawing themselves
+const MAX_FORM_DEPTH: usize = 8;
+
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// m1 then m2, in the row vector convention of the spec
+fn multiply(m1: &Matrix, m2: &Matrix) -> Matrix {
+    [
+        m1[0] * m2[0] + m1[1] * m2[2],
+        m1[0] * m2[1] + m1[1] * m2[3],
+        m1[2] * m2[0] + m1[3] * m2[2],
+        m1[2] * m2[1] + m1[3] * m2[3],
+        m1[4] * m2[0] + m1[5] * m2[2] + m2[4],
+        m1[4] * m2[1] + m1[5] * m2[3] + m2[5],
+    ]
+}
+
+fn translate(x: f64, y: f64) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, x, y]
+}
+
+fn matrix(object: Option<&PdfObject>) -> Option<Matrix> {
+    let values = object?.as_array()?.iter().map(PdfObject::as_f64).collect::<Option<Vec<_>>>()?;
+    values.try_into().ok()
+}
+
+/// A `/ToUnicode` CMap: the byte lengths codes come in and the text each code stands for.
+#[derive(Debug, Default)]
+pub struct ToUnicode {
+    /// codespace ranges as the low and high bytes of each position
+    codespace: Vec<(Vec<u8>, Vec<u8>)>,
+    chars: HashMap<Vec<u8>, String>,
+    /// low, high and the UTF-16 of low or one string per code
+    ranges: Vec<(Vec<u8>, Vec<u8>, RangeTarget)>,
+}
+
+#[derive(Debug)]
+enum RangeTarget {
+    Start(Vec<u16>),
+    Each(Vec<String>),
+}
+
+impl ToUnicode {
+    pub fn parse(data: Vec<u8>) -> Result<ToUnicode, PdfError> {
+        let mut cmap = ToUnicode::default();
+        let mut lexer = Lexer::new(ArrayRandomAccessSource::new(data));
+        let mut section: Option<Vec<u8>> = None;
+        let mut operands: Vec<Token> = Vec::new();
+        while let Some((_, token)) = lexer.next_token()? {
+            match token {
+                Token::Keyword(word) if word.starts_with(b"begin") => {
+                    section = Some(word[5..].to_vec());
+                    operands.clear();
+                }
+                Token::Keyword(word) if word.starts_with(b"end") => {
+                    section = None;
+                    operands.clear();
+                }
+                Token::ArrayStart if section.as_deref() == Some(b"bfrange") => {
+                    let mut strings = Vec::new();
+                    while let Some((_, token)) = lexer.next_token()? {
+                        match token {
+                            Token::HexString(string) | Token::LiteralString(string) => strings.push(utf16_text(&string)),
+                            Token::ArrayEnd => break,
+                            _ => {}
+                        }
+                    }
+                    if let [Token::HexString(low), Token::HexString(high)] = &operands[..] {
+                        cmap.ranges.push((low.clone(), high.clone(), RangeTarget::Each(strings)));
+                    }
+                    operands.clear();
+                }
+                token => {
+                    operands.push(token);
+                    cmap.take(section.as_deref(), &mut operands);
+                }
+            }
+        }
+        Ok(cmap)
+    }
+
+    /// consume operands once a whole entry of the section is there
+    fn take(&mut self, section: Option<&[u8]>, operands: &mut Vec<Token>) {
+        match (section, &operands[..]) {
+            (Some(b"codespacerange"), [Token::HexString(low), Token::HexString(high)]) =>
+                self.codespace.push((low.clone(), high.clone())),
+            (Some(b"bfchar"), [Token::HexString(code), Token::HexString(target)]) =>
+                { self.chars.insert(code.clone(), utf16_text(target)); }
+            (Some(b"bfchar"), [Token::HexString(code), Token::Name(name)]) =>
+                { self.chars.insert(code.clone(), glyph_to_char(name).map(String::from).unwrap_or_default()); }
+            (Some(b"bfrange"), [Token::HexString(low), Token::HexString(high), Token::HexString(target)]) =>
+                self.ranges.push((low.clone(), high.clone(), RangeTarget::Start(utf16(target)))),
+            (Some(b"codespacerange" | b"bfchar"), [_, _]) | (Some(b"bfrange"), [_, _, _]) => {}
+            _ => return,
+        }
+        operands.clear();
+    }
+
+    /// length of the code at the start of bytes, from the codespace ranges
+    fn code_length(&self, bytes: &[u8]) -> Option<usize> {
+        self.codespace.iter()
+            .filter(|(low, high)| low.len() <= bytes.len() && low.len() == high.len())
+            .find(|(low, high)| (0..low.len()).all(|index| low[index] <= bytes[index] && bytes[index] <= high[index]))
+            .map(|(low, _)| low.len())
+    }
+
+    pub fn lookup(&self, code: &[u8]) -> Option<String> {
+        if let Some(text) = self.chars.get(code) {
+            return Some(text.clone());
+        }
+        let value = code_value(code);
+        self.ranges.iter()
+            .filter(|(low, high, _)| low.len() == code.len() && code_value(low) <= value && value <= code_value(high))
+            .find_map(|(low, _, target)| {
+                let offset = value - code_value(low);
+                match target {
+                    // the last unit counts up through the range
+                    RangeTarget::Start(start) => {
+                        let mut units = start.clone();
+                        let last = units.last_mut()?;
+                        *last = last.wrapping_add(offset as u16);
+                        Some(String::from_utf16_lossy(&units))
+                    }
+                    RangeTarget::Each(strings) => strings.get(offset as usize).cloned(),
+                }
+            })
+    }
+}
+
+fn code_value(code: &[u8]) -> u32 {
+    code.iter().fold(0, |value, byte| value << 8 | *byte as u32)
+}
+
+fn utf16(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])).collect()
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+    String::from_utf16_lossy(&utf16(bytes))
+}
+
+struct Glyph {
+    text: String,
+    /// advance in text space units per unit of font size
+    width: f64,
+    /// a single byte code 32, the only one word spacing applies to
+    is_space: bool,
+}
+
+/// What text extraction needs from a font: how its bytes split into codes, their text and their widths.
+struct Font {
+    to_unicode: Option<ToUnicode>,
+    /// for simple fonts, the character of each byte
+    encoding: Option<[Option<char>; 256]>,
+    /// composite fonts with a UCS-2 CMap, whose codes are the characters
+    ucs2: bool,
+    widths: HashMap<u32, f64>,
+    default_width: f64,
+    /// glyph space to text space, 1/1000 for all fonts but Type3
+    width_scale: f64,
+}
+
+impl Font {
+    fn load<S: RandomAccessSource>(document: &PdfDocument<S>, dict: &Dictionary) -> Result<Font, PdfError> {
+        let to_unicode = match dict.get("ToUnicode").map(|object| document.resolve(object)).transpose()? {
+            Some(object) => match object.as_stream() {
+                Some(stream) => Some(ToUnicode::parse(document.stream_data(stream)?)?),
+                None => None,
+            },
+            None => None,
+        };
+        let number = |object: &PdfObject| document.resolve(object).ok().and_then(|object| object.as_f64());
+
+        if dict.get("Subtype").and_then(PdfObject::as_name) == Some(b"Type0") {
+            let encoding = dict.get("Encoding").and_then(PdfObject::as_name).unwrap_or(b"");
+            let ucs2 = encoding.windows(4).any(|window| window == b"UCS2" || window == b"UTF1");
+            let descendant = match dict.get("DescendantFonts").map(|fonts| document.resolve(fonts)).transpose()? {
+                Some(fonts) => match fonts.as_array().and_then(|fonts| fonts.first()) {
+                    Some(font) => document.resolve_dict(font)?,
+                    None => Dictionary::new(),
+                },
+                None => Dictionary::new(),
+            };
+            // /W holds `first [w1 w2 ...]` and `first last w` runs
+            let mut widths = HashMap::new();
+            if let Some(runs) = descendant.get("W").map(|runs| document.resolve(runs)).transpose()? {
+                let runs = runs.as_array().cloned().unwrap_or_default();
+                let mut index = 0;
+                while index + 1 < runs.len() {
+                    let first = number(&runs[index]).unwrap_or(0.0) as u32;
+                    match document.resolve(&runs[index + 1])?.as_array() {
+                        Some(list) => {
+                            // codes past u32::MAX cannot occur, the run stops there
+                            for (offset, width) in list.iter().enumerate() {
+                                let Some(code) = u32::try_from(offset).ok().and_then(|offset| first.checked_add(offset)) else {
+                                    break;
+                                };
+                                widths.insert(code, number(width).unwrap_or(0.0));
+                            }
+                            index += 2;
+                        }
+                        None => {
+                            let last = number(&runs[index + 1]).unwrap_or(0.0) as u32;
+                            let width = runs.get(index + 2).and_then(number).unwrap_or(0.0);
+                            for code in first..=last.min(first.saturating_add(0xFFFF)) {
+                                widths.insert(code, width);
+                            }
+                            index += 3;
+                        }
+                    }
+                }
+            }
+            let default_width = descendant.get("DW").and_then(number).unwrap_or(1000.0);
+            return Ok(Font { to_unicode, encoding: None, ucs2, widths, default_width, width_scale: 0.001 });
+        }
+
+        let mut encoding = named_encoding(b"StandardEncoding");
+        match dict.get("Encoding").map(|object| document.resolve(object)).transpose()?.as_deref() {
+            Some(PdfObject::Name(name)) => encoding = named_encoding(name).or(encoding),
+            Some(PdfObject::Dictionary(encoding_dict)) => {
+                if let Some(base) = encoding_dict.get("BaseEncoding").and_then(PdfObject::as_name) {
+                    encoding = named_encoding(base).or(encoding);
+                }
+                // /Differences: a code, then the glyph names of it and the codes following it
+                let differences = encoding_dict.get("Differences").and_then(PdfObject::as_array);
+                if let (Some(table), Some(differences)) = (encoding.as_mut(), differences) {
+                    // the codes come from the file and may be anything, only those of a byte are used
+                    let mut code = 0i64;
+                    for entry in differences {
+                        match entry {
+                            PdfObject::Integer(start) => code = *start,
+                            PdfObject::Name(name) => {
+                                if (0..256).contains(&code) {
+                                    table[code as usize] = glyph_to_char(name);
+                                }
+                                code = code.saturating_add(1);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let first_char = dict.get("FirstChar").and_then(number).unwrap_or(0.0) as u32;
+        let widths = match dict.get("Widths").map(|widths| document.resolve(widths)).transpose()? {
+            Some(widths) => widths.as_array().into_iter().flatten().enumerate()
+                .map(|(index, width)| (first_char + index as u32, number(width).unwrap_or(0.0)))
+                .collect(),
+            None => HashMap::new(),
+        };
+        let width_scale = match matrix(dict.get("FontMatrix")) {
+            Some(font_matrix) if dict.get("Subtype").and_then(PdfObject::as_name) == Some(b"Type3") => font_matrix[0],
+            _ => 0.001,
+        };
+        // without /Widths, as for the standard 14 fonts, half an em is a fair guess
+        let default_width = if widths.is_empty() { 500.0 } else { 0.0 };
+        Ok(Font { to_unicode, encoding, ucs2: false, widths, default_width, width_scale })
+    }
+
+    fn glyphs(&self, bytes: &[u8]) -> Vec<Glyph> {
+        let mut glyphs = Vec::new();
+        let mut index = 0;
+        while index < bytes.len() {
+            let rest = &bytes[index..];
+            let length = self.to_unicode.as_ref().and_then(|cmap| cmap.code_length(rest))
+                .unwrap_or(if self.encoding.is_some() { 1 } else { 2 })
+                .min(rest.len());
+            let code = &rest[..length];
+            index += length;
+
+            let value = code_value(code);
+            let text = self.to_unicode.as_ref().and_then(|cmap| cmap.lookup(code))
+                .or_else(|| self.encoding.as_ref().and_then(|table| table[value as usize & 0xFF]).map(String::from))
+                .or_else(|| if self.ucs2 { char::from_u32(value).map(String::from) } else { None })
+                .unwrap_or_default();
+            let width = self.widths.get(&value).copied().unwrap_or(self.default_width) * self.width_scale;
+            glyphs.push(Glyph { text, width, is_space: length == 1 && value == 32 });
+        }
+        glyphs
+    }
+}
+
+/// Text shown in one go, positioned in device space.
+#[derive(Debug)]
+struct Span {
+    x: f64,
+    y: f64,
+    end_x: f64,
+    size: f64,
+    text: String,
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Matrix,
+    char_spacing: f64,
+    word_spacing: f64,
+    horizontal_scale: f64,
+    leading: f64,
+    font: Option<Rc<Font>>,
+    font_size: f64,
+    rise: f64,
+}
+
+impl Default for GraphicsState {
+    fn default() -> GraphicsState {
+        GraphicsState {
+            ctm: IDENTITY,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scale: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// Extracts the text of pages, keeping the fonts it loaded for the following pages.
+pub struct TextExtractor<'a, S: RandomAccessSource> {
+    document: &'a PdfDocument<S>,
+    fonts: HashMap<ObjectId, Rc<Font>>,
+}
+
+/// The content stream interpreter state for one page.
+struct Interpreter {
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    text_matrix: Matrix,
+    line_matrix: Matrix,
+    spans: Vec<Span>,
+}
+
+impl<'a, S: RandomAccessSource> TextExtractor<'a, S> {
+    pub fn new(document: &'a PdfDocument<S>) -> TextExtractor<'a, S> {
+        TextExtractor { document, fonts: HashMap::new() }
+    }
+
+    /// the text of page in reading order, one line of text per line
+    pub fn page_text(&mut self, page: &Page) -> Result<String, PdfError> {
+        let mut content = Vec::new();
+        if let Some(contents) = page.dict.get("Contents") {
+            let contents = self.document.resolve(contents)?;
+            let streams = match &*contents {
+                PdfObject::Array(streams) => streams.clone(),
+                _ => vec![(*contents).clone()],
+            };
+            // the streams of an array are one content stream split at token boundaries
+            for stream in streams {
+                if let Some(stream) = self.document.resolve(&stream)?.as_stream() {
+                    content.extend_from_slice(&self.document.stream_data(stream)?);
+                    content.push(b'\n');
+                }
+            }
+        }
+        let resources = match page.dict.get("Resources") {
+            Some(resources) => self.document.resolve_dict(resources)?,
+            None => Dictionary::new(),
+        };
+        let mut interpreter = Interpreter {
+            state: GraphicsState::default(),
+            stack: Vec::new(),
+            text_matrix: IDENTITY,
+            line_matrix: IDENTITY,
+            spans: Vec::new(),
+        };
+        self.run(&mut interpreter, content, &resources, 0)?;
+        Ok(layout(interpreter.spans))
+    }
+
+    fn run(&mut self, interpreter: &mut Interpreter, content: Vec<u8>, resources: &Dictionary, depth: usize)
+           -> Result<(), PdfError> {
+        let mut parser = Parser::new(ArrayRandomAccessSource::new(content));
+        let mut operands: Vec<PdfObject> = Vec::new();
+        loop {
+            let operator = match parser.peek_token(0)? {
+                None => return Ok(()),
+                Some(Token::Keyword(word)) if !matches!(&word[..], b"true" | b"false" | b"null") => word.clone(),
+                Some(_) => {
+                    // a stray token drops the operands gathered so far, like a viewer would
+                    match parser.parse_object() {
+                        Ok(operand) => operands.push(operand),
+                        Err(PdfError::Syntax { .. }) => operands.clear(),
+                        Err(error) => return Err(error),
+                    }
+                    continue;
+                }
+            };
+            parser.next_token()?;
+            match &operator[..] {
+                b"ID" => skip_inline_image(&mut parser)?,
+                b"Do" if depth < MAX_FORM_DEPTH => {
+                    if let Some(PdfObject::Name(name)) = operands.first() {
+                        self.draw_form(interpreter, name, resources, depth)?;
+                    }
+                }
+                b"Tf" => {
+                    if let [PdfObject::Name(name), size] = &operands[..] {
+                        interpreter.state.font = self.font(resources, name)?;
+                        interpreter.state.font_size = size.as_f64().unwrap_or(0.0);
+                    }
+                }
+                _ => interpreter.operator(&operator, &operands),
+            }
+            operands.clear();
+        }
+    }
+
+    fn font(&mut self, resources: &Dictionary, name: &[u8]) -> Result<Option<Rc<Font>>, PdfError> {
+        let fonts = match resources.get("Font") {
+            Some(fonts) => self.document.resolve_dict(fonts)?,
+            None => return Ok(None),
+        };
+        let entry = match fonts.iter().find(|(key, _)| key.as_slice() == name) {
+            Some((_, entry)) => entry.clone(),
+            None => return Ok(None),
+        };
+        if let Some(font) = entry.as_reference().and_then(|id| self.fonts.get(&id)) {
+            return Ok(Some(Rc::clone(font)));
+        }
+        let font = Rc::new(Font::load(self.document, &self.document.resolve_dict(&entry)?)?);
+        if let Some(id) = entry.as_reference() {
+            self.fonts.insert(id, Rc::clone(&font));
+        }
+        Ok(Some(font))
+    }
+
+    fn draw_form(&mut self, interpreter: &mut Interpreter, name: &[u8], resources: &Dictionary, depth: usize)
+                 -> Result<(), PdfError> {
+        let xobjects = match resources.get("XObject") {
+            Some(xobjects) => self.document.resolve_dict(xobjects)?,
+            None => return Ok(()),
+        };
+        let form = match xobjects.iter().find(|(key, _)| key.as_slice() == name) {
+            Some((_, form)) => self.document.resolve(form)?,
+            None => return Ok(()),
+        };
+        let stream = match form.as_stream() {
+            Some(stream) if stream.dict.get("Subtype").and_then(PdfObject::as_name) == Some(b"Form") => stream,
+            _ => return Ok(()),
+        };
+        // a form without resources uses those of the page drawing it
+        let form_resources = match stream.dict.get("Resources") {
+            Some(form_resources) => self.document.resolve_dict(form_resources)?,
+            None => resources.clone(),
+        };
+        let content = self.document.stream_data(stream)?;
+
+        interpreter.stack.push(interpreter.state.clone());
+        if let Some(form_matrix) = matrix(stream.dict.get("Matrix")) {
+            interpreter.state.ctm = multiply(&form_matrix, &interpreter.state.ctm);
+        }
+        let result = self.run(interpreter, content, &form_resources, depth + 1);
+        if let Some(state) = interpreter.stack.pop() {
+            interpreter.state = state;
+        }
+        result
+    }
+}
+
+impl Interpreter {
+    fn operator(&mut self, operator: &[u8], operands: &[PdfObject]) {
+        let number = |index: usize| operands.get(index).and_then(PdfObject::as_f64).unwrap_or(0.0);
+        match operator {
+            b"q" => self.stack.push(self.state.clone()),
+            b"Q" => {
+                if let Some(state) = self.stack.pop() {
+                    self.state = state;
+                }
+            }
+            b"cm" if operands.len() == 6 => {
+                let m = [number(0), number(1), number(2), number(3), number(4), number(5)];
+                self.state.ctm = multiply(&m, &self.state.ctm);
+            }
+            b"BT" => {
+                self.text_matrix = IDENTITY;
+                self.line_matrix = IDENTITY;
+            }
+            b"Tc" => self.state.char_spacing = number(0),
+            b"Tw" => self.state.word_spacing = number(0),
+            b"Tz" => self.state.horizontal_scale = number(0) / 100.0,
+            b"TL" => self.state.leading = number(0),
+            b"Ts" => self.state.rise = number(0),
+            b"Td" => self.move_line(number(0), number(1)),
+            b"TD" => {
+                self.state.leading = -number(1);
+                self.move_line(number(0), number(1));
+            }
+            b"Tm" if operands.len() == 6 => {
+                self.line_matrix = [number(0), number(1), number(2), number(3), number(4), number(5)];
+                self.text_matrix = self.line_matrix;
+            }
+            b"T*" => self.next_line(),
+            b"Tj" => {
+                if let Some(string) = operands.first().and_then(PdfObject::as_string) {
+                    self.show(string);
+                }
+            }
+            b"'" => {
+                self.next_line();
+                if let Some(string) = operands.first().and_then(PdfObject::as_string) {
+                    self.show(string);
+                }
+            }
+            b"\"" => {
+                self.state.word_spacing = number(0);
+                self.state.char_spacing = number(1);
+                self.next_line();
+                if let Some(string) = operands.get(2).and_then(PdfObject::as_string) {
+                    self.show(string);
+                }
+            }
+            b"TJ" => {
+                for element in operands.first().and_then(PdfObject::as_array).into_iter().flatten() {
+                    match element {
+                        PdfObject::String(string) => self.show(string),
+                        // adjustments are thousandths of an em, moving left when positive
+                        adjustment => {
+                            let shift = -adjustment.as_f64().unwrap_or(0.0) / 1000.0
+                                * self.state.font_size * self.state.horizontal_scale;
+                            self.text_matrix = multiply(&translate(shift, 0.0), &self.text_matrix);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn move_line(&mut self, x: f64, y: f64) {
+        self.line_matrix = multiply(&translate(x, y), &self.line_matrix);
+        self.text_matrix = self.line_matrix;
+    }
+
+    fn next_line(&mut self) {
+        let leading = self.state.leading;
+        self.move_line(0.0, -leading);
+    }
+
+    /// where the text matrix puts the origin of the next glyph, and the font size on the device
+    fn position(&self) -> (f64, f64, f64) {
+        let m = multiply(&self.text_matrix, &self.state.ctm);
+        let rise = self.state.rise;
+        let size = self.state.font_size * (m[2] * m[2] + m[3] * m[3]).sqrt();
+        (m[2] * rise + m[4], m[3] * rise + m[5], size)
+    }
+
+    fn show(&mut self, bytes: &[u8]) {
+        let font = match &self.state.font {
+            Some(font) => Rc::clone(font),
+            None => return,
+        };
+        let (x, y, size) = self.position();
+        let mut text = String::new();
+        for glyph in font.glyphs(bytes) {
+            text.push_str(&glyph.text);
+            let spacing = self.state.char_spacing + if glyph.is_space { self.state.word_spacing } else { 0.0 };
+            let advance = (glyph.width * self.state.font_size + spacing) * self.state.horizontal_scale;
+            self.text_matrix = multiply(&translate(advance, 0.0), &self.text_matrix);
+        }
+        let (end_x, _, _) = self.position();
+        if !text.is_empty() {
+            self.spans.push(Span { x, y, end_x, size, text });
+        }
+    }
+}
+
+/// skip the data of an inline image, which runs from after `ID` and a whitespace byte to `EI` between whitespace
+fn skip_inline_image(parser: &mut Parser<ArrayRandomAccessSource<Vec<u8>>>) -> Result<(), PdfError> {
+    let start = parser.offset() as usize + 1;
+    let data = parser.source().as_bytes();
+    let end = (start..data.len().saturating_sub(1))
+        .find(|index| {
+            data[*index..].starts_with(b"EI") && *index > 0 && is_whitespace(data[index - 1])
+                && data.get(index + 2).is_none_or(|byte| is_whitespace(*byte))
+        })
+        .map_or(data.len(), |index| index + 2);
+    parser.seek(end as u64)
+}
+
+/// put spans in reading order: lines from top to bottom, spans left to right, with a space wherever the gap between
+/// two spans is wider than a narrow space
+fn layout(mut spans: Vec<Span>) -> String {
+    spans.sort_by(|a, b| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+    let mut lines: Vec<Vec<Span>> = Vec::new();
+    for span in spans {
+        match lines.last_mut() {
+            // a span belongs to the line when its baseline is within half a line of it
+            Some(line) if (line[0].y - span.y).abs() < line[0].size.max(span.size) * 0.5 => line.push(span),
+            _ => lines.push(vec![span]),
+        }
+    }
+
+    let mut text = String::new();
+    for mut line in lines {
+        line.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let mut previous: Option<&Span> = None;
+        for span in &line {
+            if let Some(previous) = previous {
+                let gap = span.x - previous.end_x;
+                let spaced = previous.text.ends_with(char::is_whitespace) || span.text.starts_with(char::is_whitespace);
+                if gap > span.size.max(previous.size) * 0.15 && !spaced {
+                    text.push(' ');
+                }
+            }
+            text.push_str(&span.text);
+            previous = Some(span);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// the text of every page of document, a page that fails to extract not stopping the pages after it
+pub fn extract_text<S: RandomAccessSource>(document: &PdfDocument<S>) -> Result<Vec<Result<String, PdfError>>, PdfError> {
+    let mut extractor = TextExtractor::new(document);
+    Ok(document.pages()?.iter().map(|page| extractor.page_text(page)).collect())
+}
+
+#[test]
+fn to_unicode_test() {
+    let cmap = ToUnicode::parse(b"/CIDInit /ProcSet findresource begin 12 dict begin begincmap
+/CMapName /Test def
+2 begincodespacerange <00> <7F> <8000> <FFFF> endcodespacerange
+2 beginbfchar <01> <0048> <8001> <D83DDE00> endbfchar
+2 beginbfrange <10> <12> <0061> <8010> <8011> [<0066006C> (\\000x)] endbfrange
+endcmap CMapName currentdict /CMap defineresource pop end end".to_vec()).unwrap();
+
+    assert_eq!(cmap.code_length(&[0x01, 0x80]), Some(1));
+    assert_eq!(cmap.code_length(&[0x80, 0x01]), Some(2));
+    assert_eq!(cmap.code_length(&[0x80]), None);
+    assert_eq!(cmap.lookup(&[0x01]).as_deref(), Some("H"));
+    assert_eq!(cmap.lookup(&[0x80, 0x01]).as_deref(), Some("😀"));
+    assert_eq!(cmap.lookup(&[0x11]).as_deref(), Some("b"));
+    assert_eq!(cmap.lookup(&[0x80, 0x10]).as_deref(), Some("fl"));
+    assert_eq!(cmap.lookup(&[0x80, 0x11]).as_deref(), Some("x"));
+    assert_eq!(cmap.lookup(&[0x13]), None);
+}
+
+#[cfg(test)]
+fn stream(entries: &str, content: &str) -> String {
+    format!("<< {}/Length {} >>\nstream\n{}\nendstream", entries, content.len(), content)
+}
+
+#[test]
+fn hostile_font_test() {
+    use crate::pdf::object::ObjectId;
+    use crate::pdf::xref::build_pdf;
+
+    let document = PdfDocument::open(ArrayRandomAccessSource::new(build_pdf(&[
+        "<< /Type /Catalog >>",
+        "<< /Type /Font /Subtype /Type0 /Encoding /Identity-H \
+/DescendantFonts [<< /W [4294967294 [500 600 700] 4294967290 4294967295 300] >>] >>",
+        "<< /Type /Font /Subtype /Type1 /Encoding << /Differences [-1 /a /b 9223372036854775807 /c /d 66 /x] >> >>",
+    ]))).unwrap();
+    let font = |number| Font::load(&document, document.get(ObjectId::new(number, 0)).unwrap().as_dict().unwrap()).unwrap();
+
+    // runs ending past the largest code stop there instead of overflowing
+    let composite = font(2);
+    assert_eq!(composite.widths.get(&4294967294), Some(&300.0));
+    assert_eq!(composite.widths.get(&u32::MAX), Some(&300.0));
+    assert_eq!(composite.widths.len(), 6);
+
+    // negative and huge codes are skipped, except where they count up into the byte range
+    let encoding = font(3).encoding.unwrap();
+    assert_eq!((encoding[0], encoding[65], encoding[66]), (Some('b'), Some('A'), Some('x')));
+}
+
+#[test]
+fn extract_text_test() {
+    use crate::pdf::xref::build_pdf;
+
+    let cmap = "begincmap 1 begincodespacerange <0000> <FFFF> endcodespacerange \
+2 beginbfchar <0001> <004B> <0002> <00F6> endbfchar 1 beginbfrange <0003> <0005> <006C> endbfrange endcmap";
+    // the second line is drawn first, and the first line is drawn in two pieces with a kerned TJ
+    let page_one = ["BT /F1 12 Tf 72 680 Td (second line) Tj ET \
+q 1 0 0 1 72 700 cm BT /F1 12 Tf 0 0 Td [(Hel) -20 (lo) -1000 (world)] TJ ( again) Tj ET Q",
+        "BT /F2 10 Tf 1 0 0 1 72 660 Tm (\\001\\002) Tj 14 TL T* (\\003\\004\\005\\003) ' ET \
+BI /W 2 /H 2 /BPC 8 /CS /G ID \x01EI\x02\x03 EI \
+BT /F3 10 Tf 72 620 Td <000100020003> Tj ET /Fm1 Do"];
+    let page_two = "BT /F1 12 Tf 14 TL 72 700 Td 0 5 (A) \" (B) ' ET";
+    let document = PdfDocument::open(ArrayRandomAccessSource::new(build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /Resources << /Font << /F1 5 0 R /F2 6 0 R /F3 7 0 R >> \
+/XObject << /Fm1 10 0 R >> >> >>",
+        "<< /Type /Page /Parent 2 0 R /Contents [8 0 R 11 0 R] >>",
+        "<< /Type /Page /Parent 2 0 R /Contents 9 0 R >>",
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>",
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Custom /Encoding << /Differences [1 /K /odieresis /l /n /ffi] >> >>",
+        "<< /Type /Font /Subtype /Type0 /BaseFont /Composite /Encoding /Identity-H /ToUnicode 12 0 R \
+/DescendantFonts [<< /Subtype /CIDFontType2 /W [1 [600 500]] >>] >>",
+        &stream("", page_one[0]),
+        &stream("", page_two),
+        &stream("/Type /XObject /Subtype /Form /BBox [0 0 612 792] ", "BT /F1 8 Tf 1 0 0 1 300 100 Tm (footer) Tj ET"),
+        &stream("", page_one[1]),
+        &stream("", cmap),
+    ]))).unwrap();
+
+    let text = extract_text(&document).unwrap();
+    assert_eq!(text.len(), 2);
+    assert_eq!(text[0].as_ref().unwrap(), "Hello world again\nsecond line\nKö\nlnﬃl\nKöl\nfooter\n");
+    assert_eq!(text[1].as_ref().unwrap(), "A\nB\n");
+}
\ No newline at end of file
@@ -0,0 +1,417 @@
+use std::collections::BTreeMap;
+
+use crate::example::random_access_sink::RandomAccessSink;
+use crate::example::random_access_source::RandomAccessSource;
+use crate::pdf::document::PdfDocument;
+use crate::pdf::error::PdfError;
+use crate::pdf::lexer::{is_delimiter, is_whitespace};
+use crate::pdf::object::{Dictionary, ObjectId, PdfObject};
+
+/// trailer entries that describe a single section rather than the document, never carried into an update
+const SECTION_KEYS: [&str; 13] = [
+    "Prev", "XRefStm", "Size", "Type", "W", "Index", "Length", "Filter", "DecodeParms", "F", "FFilter", "FDecodeParms",
+    "DL",
+];
+
+/// append the PDF syntax of object to out. streams are written by `IncrementalUpdate` together with their data
+pub fn write_object(out: &mut Vec<u8>, object: &PdfObject) -> Result<(), PdfError> {
+    match object {
+        PdfObject::Null => out.extend_from_slice(b"null"),
+        PdfObject::Boolean(value) => out.extend_from_slice(if *value { b"true" } else { b"false" }),
+        PdfObject::Integer(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        // Display never uses an exponent, which PDF does not have
+        PdfObject::Real(value) if value.is_finite() => out.extend_from_slice(value.to_string().as_bytes()),
+        PdfObject::Real(_) => out.push(b'0'),
+        PdfObject::Name(name) => write_name(out, name),
+        PdfObject::String(string) => {
+            out.push(b'(');
+            for byte in string {
+                match byte {
+                    b'(' | b')' | b'\\' => out.extend_from_slice(&[b'\\', *byte]),
+                    // a raw CR would be read back as LF
+                    b'\r' => out.extend_from_slice(b"\\r"),
+                    _ => out.push(*byte),
+                }
+            }
+            out.push(b')');
+        }
+        PdfObject::Array(array) => {
+            out.push(b'[');
+            for (index, element) in array.iter().enumerate() {
+                if index > 0 {
+                    out.push(b' ');
+                }
+                write_object(out, element)?;
+            }
+            out.push(b']');
+        }
+        PdfObject::Dictionary(dict) => write_dictionary(out, dict)?,
+        PdfObject::Reference(id) => out.extend_from_slice(format!("{} {} R", id.number, id.generation).as_bytes()),
+        PdfObject::Stream(_) => return Err(PdfError::Malformed("a stream is written with its data, not as a value".to_string())),
+    }
+    Ok(())
+}
+
+fn write_name(out: &mut Vec<u8>, name: &[u8]) {
+    out.push(b'/');
+    for byte in name {
+        if (0x21..=0x7E).contains(byte) && *byte != b'#' && !is_delimiter(*byte) && !is_whitespace(*byte) {
+            out.push(*byte);
+        } else {
+            out.extend_from_slice(format!("#{:02X}", byte).as_bytes());
+        }
+    }
+}
+
+fn write_dictionary(out: &mut Vec<u8>, dict: &Dictionary) -> Result<(), PdfError> {
+    out.extend_from_slice(b"<<");
+    for (key, value) in dict.iter() {
+        write_name(out, key);
+        out.push(b' ');
+        write_object(out, value)?;
+    }
+    out.extend_from_slice(b">>");
+    Ok(())
+}
+
+enum Change {
+    Object(PdfObject),
+    Stream(Dictionary, Vec<u8>),
+    Free,
+}
+
+/// Objects to append to a PDF as an incremental update: the changed and new objects, a cross-reference section for
+/// them and a trailer pointing back to the previous section with `/Prev`.
+///
+/// The original bytes are never rewritten, so what they hold, signatures included, stays valid.
+pub struct IncrementalUpdate {
+    changes: BTreeMap<u32, (u16, Change)>,
+    trailer: Dictionary,
+    next_number: u32,
+    prev: u64,
+    original_length: u64,
+    ends_with_eol: bool,
+    /// the newest section is a stream, so readers may not understand a table
+    xref_stream: bool,
+}
+
+impl IncrementalUpdate {
+    pub fn new<S: RandomAccessSource>(document: &PdfDocument<S>) -> Result<IncrementalUpdate, PdfError> {
+        let original = document.trailer();
+        if original.get("Encrypt").is_some() {
+            return Err(PdfError::Malformed("updating encrypted documents is not supported".to_string()));
+        }
+        let source = document.source();
+        let original_length = source.length()?;
+        let last = match original_length {
+            0 => None,
+            length => source.get(length - 1)?,
+        };
+
+        let mut trailer = original.clone();
+        for key in SECTION_KEYS {
+            trailer.remove(key);
+        }
+        let highest = document.xref().entries().keys().next_back().map_or(0, |number| number + 1);
+        let size = original.get("Size").and_then(PdfObject::as_i64).unwrap_or(0).max(0) as u32;
+        Ok(IncrementalUpdate {
+            changes: BTreeMap::new(),
+            trailer,
+            next_number: size.max(highest).max(1),
+            prev: document.xref().startxref(),
+            original_length,
+            ends_with_eol: matches!(last, Some(b'\n' | b'\r')),
+            xref_stream: original.has_type("XRef"),
+        })
+    }
+
+    /// replace the object with id, or add it under that id
+    pub fn set(&mut self, id: ObjectId, object: PdfObject) {
+        self.change(id, Change::Object(object));
+    }
+
+    /// add object under a new number
+    pub fn add(&mut self, object: PdfObject) -> ObjectId {
+        let id = self.allocate();
+        self.set(id, object);
+        id
+    }
+
+    /// replace or add a stream of data. `/Length` is set from data, a `/Filter` in dict must match how data is encoded
+    pub fn set_stream(&mut self, id: ObjectId, dict: Dictionary, data: Vec<u8>) {
+        self.change(id, Change::Stream(dict, data));
+    }
+
+    pub fn add_stream(&mut self, dict: Dictionary, data: Vec<u8>) -> ObjectId {
+        let id = self.allocate();
+        self.set_stream(id, dict, data);
+        id
+    }
+
+    /// free the object, which readers then resolve to null
+    pub fn delete(&mut self, id: ObjectId) {
+        // the next object to use the number gets the next generation
+        self.changes.insert(id.number, (id.generation.saturating_add(1), Change::Free));
+    }
+
+    /// the trailer the update is written with, to change `/Root` or `/Info`
+    pub fn trailer_mut(&mut self) -> &mut Dictionary {
+        &mut self.trailer
+    }
+
+    fn allocate(&mut self) -> ObjectId {
+        let id = ObjectId::new(self.next_number, 0);
+        self.next_number += 1;
+        id
+    }
+
+    fn change(&mut self, id: ObjectId, change: Change) {
+        self.next_number = self.next_number.max(id.number + 1);
+        self.changes.insert(id.number, (id.generation, change));
+    }
+
+    /// append the update to sink, which must hold exactly the original file. returns the offset of the new section
+    pub fn write<K: RandomAccessSink>(&self, sink: &mut K) -> Result<u64, PdfError> {
+        let length = sink.length()?;
+        if length != self.original_length {
+            return Err(PdfError::Malformed(format!("the sink holds {} bytes but the document has {}",
+                                                   length, self.original_length)));
+        }
+        let mut out = Vec::new();
+        if !self.ends_with_eol {
+            out.push(b'\n');
+        }
+
+        // number, generation and offset, which is None for free entries
+        let mut entries: Vec<(u32, u16, Option<u64>)> = Vec::with_capacity(self.changes.len() + 1);
+        for (number, (generation, change)) in &self.changes {
+            let offset = length + out.len() as u64;
+            match change {
+                Change::Free => {
+                    entries.push((*number, *generation, None));
+                    continue;
+                }
+                Change::Object(object) => {
+                    out.extend_from_slice(format!("{} {} obj\n", number, generation).as_bytes());
+                    write_object(&mut out, object)?;
+                }
+                Change::Stream(dict, data) => {
+                    out.extend_from_slice(format!("{} {} obj\n", number, generation).as_bytes());
+                    write_stream(&mut out, dict, data)?;
+                }
+            }
+            out.extend_from_slice(b"\nendobj\n");
+            entries.push((*number, *generation, Some(offset)));
+        }
+
+        let startxref = length + out.len() as u64;
+        let mut trailer = self.trailer.clone();
+        trailer.insert("Prev", PdfObject::Integer(self.prev as i64));
+        if self.xref_stream {
+            // the stream lists itself, under the first number nothing else uses
+            let number = self.next_number;
+            entries.push((number, 0, Some(startxref)));
+            trailer.insert("Size", PdfObject::Integer(number as i64 + 1));
+            let (dict, data) = xref_stream(trailer, &entries);
+            out.extend_from_slice(format!("{} 0 obj\n", number).as_bytes());
+            write_stream(&mut out, &dict, &data)?;
+            out.extend_from_slice(b"\nendobj\n");
+        } else {
+            trailer.insert("Size", PdfObject::Integer(self.next_number as i64));
+            out.extend_from_slice(b"xref\n");
+            for run in runs(&entries) {
+                out.extend_from_slice(format!("{} {}\n", run[0].0, run.len()).as_bytes());
+                for (_, generation, offset) in run {
+                    match offset {
+                        Some(offset) => out.extend_from_slice(format!("{:010} {:05} n\r\n", offset, generation).as_bytes()),
+                        None => out.extend_from_slice(format!("{:010} {:05} f\r\n", 0, generation).as_bytes()),
+                    }
+                }
+            }
+            out.extend_from_slice(b"trailer\n");
+            write_dictionary(&mut out, &trailer)?;
+            out.push(b'\n');
+        }
+        out.extend_from_slice(format!("startxref\n{}\n%%EOF\n", startxref).as_bytes());
+
+        sink.write_at(length, &out)?;
+        sink.flush()?;
+        Ok(startxref)
+    }
+}
+
+fn write_stream(out: &mut Vec<u8>, dict: &Dictionary, data: &[u8]) -> Result<(), PdfError> {
+    let mut dict = dict.clone();
+    dict.insert("Length", PdfObject::Integer(data.len() as i64));
+    write_dictionary(out, &dict)?;
+    out.extend_from_slice(b"\nstream\n");
+    out.extend_from_slice(data);
+    out.extend_from_slice(b"\nendstream");
+    Ok(())
+}
+
+/// entries split into runs of consecutive numbers, one subsection each
+fn runs(entries: &[(u32, u16, Option<u64>)]) -> Vec<&[(u32, u16, Option<u64>)]> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for index in 1..=entries.len() {
+        if index == entries.len() || entries[index].0 != entries[index - 1].0 + 1 {
+            runs.push(&entries[start..index]);
+            start = index;
+        }
+    }
+    runs
+}
+
+/// the dictionary and rows of an unfiltered xref stream, the offset field as wide as the largest offset needs
+fn xref_stream(trailer: Dictionary, entries: &[(u32, u16, Option<u64>)]) -> (Dictionary, Vec<u8>) {
+    let largest = entries.iter().filter_map(|(_, _, offset)| *offset).max().unwrap_or(0);
+    let width = (8 - largest.leading_zeros() as usize / 8).max(1);
+    let mut data = Vec::with_capacity(entries.len() * (width + 3));
+    let mut index = Vec::new();
+    for run in runs(entries) {
+        index.push(PdfObject::Integer(run[0].0 as i64));
+        index.push(PdfObject::Integer(run.len() as i64));
+        for (_, generation, offset) in run {
+            data.push(if offset.is_some() { 1 } else { 0 });
+            data.extend_from_slice(&offset.unwrap_or(0).to_be_bytes()[8 - width..]);
+            data.extend_from_slice(&generation.to_be_bytes());
+        }
+    }
+    let mut dict = trailer;
+    dict.insert("Type", PdfObject::Name(b"XRef".to_vec()));
+    dict.insert("W", PdfObject::Array(vec![PdfObject::Integer(1), PdfObject::Integer(width as i64), PdfObject::Integer(2)]));
+    dict.insert("Index", PdfObject::Array(index));
+    (dict, data)
+}
+
+#[test]
+fn write_object_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+    use crate::pdf::object::Parser;
+
+    let mut dict = Dictionary::new();
+    dict.insert("Name", PdfObject::Name(b"with space#(x)".to_vec()));
+    dict.insert("String", PdfObject::String(b"(nested) \\ \r\n\xff".to_vec()));
+    dict.insert("Array", PdfObject::Array(vec![
+        PdfObject::Integer(-3),
+        PdfObject::Real(0.25),
+        PdfObject::Real(1e21),
+        PdfObject::Boolean(false),
+        PdfObject::Null,
+        PdfObject::Reference(ObjectId::new(7, 2)),
+    ]));
+    let object = PdfObject::Dictionary(dict);
+    let mut out = Vec::new();
+    write_object(&mut out, &object).unwrap();
+    assert!(out.windows(19).any(|window| window == b"/with#20space#23#28"));
+
+    let read = Parser::new(ArrayRandomAccessSource::new(out)).parse_object().unwrap();
+    assert_eq!(read, object);
+}
+
+#[cfg(test)]
+fn read_back(bytes: Vec<u8>) -> PdfDocument<crate::example::array_random_access_source::ArrayRandomAccessSource<Vec<u8>>> {
+    PdfDocument::open(crate::example::array_random_access_source::ArrayRandomAccessSource::new(bytes)).unwrap()
+}
+
+#[test]
+fn incremental_update_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+    use crate::example::random_access_sink::ArrayRandomAccessSink;
+    use crate::pdf::text::extract_text;
+    use crate::pdf::xref::{build_pdf, replace};
+
+    let original = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>",
+        "<< /Length 30 >>\nstream\nBT /F1 12 Tf (old text) Tj ET\nendstream",
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+    ]);
+    let document = read_back(original.clone());
+    let original_startxref = document.xref().startxref();
+
+    let mut update = IncrementalUpdate::new(&document).unwrap();
+    let contents = update.add_stream(Dictionary::new(), b"BT /F1 12 Tf (new text) Tj ET".to_vec());
+    assert_eq!(contents, ObjectId::new(6, 0));
+    let mut page = document.resolve_dict(&PdfObject::Reference(ObjectId::new(3, 0))).unwrap();
+    page.insert("Contents", PdfObject::Reference(contents));
+    page.insert("Rotate", PdfObject::Integer(90));
+    update.set(ObjectId::new(3, 0), PdfObject::Dictionary(page));
+    update.delete(ObjectId::new(4, 0));
+    let mut info = Dictionary::new();
+    info.insert("Title", PdfObject::String(b"Updated (twice)".to_vec()));
+    let info = update.add(PdfObject::Dictionary(info));
+    update.trailer_mut().insert("Info", PdfObject::Reference(info));
+
+    let mut sink = ArrayRandomAccessSink::from_vec(original.clone());
+    let startxref = update.write(&mut sink).unwrap();
+    let updated = sink.into_inner();
+    assert_eq!(&updated[..original.len()], &original[..]);
+
+    let document = read_back(updated.clone());
+    assert_eq!(document.xref().startxref(), startxref);
+    assert_eq!(document.trailer().get("Prev"), Some(&PdfObject::Integer(original_startxref as i64)));
+    assert_eq!(document.trailer().get("Size"), Some(&PdfObject::Integer(8)));
+    assert_eq!(document.page(0).unwrap().dict.get("Rotate"), Some(&PdfObject::Integer(90)));
+    assert_eq!(*document.get(ObjectId::new(4, 0)).unwrap(), PdfObject::Null);
+    assert!(document.get(ObjectId::new(5, 0)).unwrap().as_dict().unwrap().has_type("Font"));
+    let title = document.resolve_dict(document.trailer().get("Info").unwrap()).unwrap();
+    assert_eq!(title.get("Title").unwrap().as_string(), Some(&b"Updated (twice)"[..]));
+    let text = extract_text(&document).unwrap();
+    assert_eq!(text.len(), 1);
+    assert_eq!(text[0].as_ref().unwrap(), "new text\n");
+
+    // a second update chains to the first
+    let mut second = IncrementalUpdate::new(&document).unwrap();
+    second.set(ObjectId::new(4, 1), PdfObject::String(b"reused".to_vec()));
+    let mut sink = ArrayRandomAccessSink::from_vec(updated.clone());
+    second.write(&mut sink).unwrap();
+    let twice = read_back(sink.into_inner());
+    assert_eq!(*twice.get(ObjectId::new(4, 1)).unwrap(), PdfObject::String(b"reused".to_vec()));
+    assert_eq!(twice.page(0).unwrap().dict.get("Rotate"), Some(&PdfObject::Integer(90)));
+
+    // the sink must still be the original
+    assert!(IncrementalUpdate::new(&document).unwrap().write(&mut ArrayRandomAccessSink::new()).is_err());
+    let encrypted = build_pdf(&["<< /Type /Catalog >>"]);
+    let encrypted = replace(&encrypted, b"/Root 1 0 R", b"/Root 1 0 R /Encrypt 1 0 R");
+    let encrypted = PdfDocument::open(ArrayRandomAccessSource::new(encrypted)).unwrap();
+    assert!(IncrementalUpdate::new(&encrypted).is_err());
+}
+
+#[test]
+fn incremental_update_xref_stream_test() {
+    use crate::example::random_access_sink::{FileRandomAccessSink, RandomAccessSink};
+    use crate::example::random_access_source::{test_file, FileRandomAccessSouce};
+    use crate::pdf::xref::build_pdf;
+
+    // an original whose newest section is an xref stream, without a newline at the end
+    let mut original = build_pdf(&["<< /Type /Catalog /Pages 2 0 R >>", "<< /Type /Pages /Kids [] /Count 0 >>"]);
+    let prev = read_back(original.clone()).xref().startxref();
+    let three = original.len();
+    let rows = [1u8, (three >> 8) as u8, three as u8, 0];
+    original.extend_from_slice(format!("3 0 obj\n<< /Type /XRef /W [1 2 1] /Index [3 1] /Size 4 /Root 1 0 R /Prev {} \
+/Length 4 >>\nstream\n", prev).as_bytes());
+    original.extend_from_slice(&rows);
+    original.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF", three).as_bytes());
+
+    let path = test_file("pdf_update", &original);
+    let document = PdfDocument::open(FileRandomAccessSouce::new(&path).unwrap()).unwrap();
+    let mut update = IncrementalUpdate::new(&document).unwrap();
+    update.set(ObjectId::new(2, 0), PdfObject::Integer(42));
+    let mut sink = FileRandomAccessSink::new(&path).unwrap();
+    update.write(&mut sink).unwrap();
+    sink.sync().unwrap();
+
+    let updated = std::fs::read(&path).unwrap();
+    assert_eq!(&updated[..original.len()], &original[..]);
+    assert_eq!(updated[original.len()], b'\n');
+    let document = PdfDocument::open(FileRandomAccessSouce::new(&path).unwrap()).unwrap();
+    assert!(document.trailer().has_type("XRef"));
+    assert_eq!(document.trailer().get("Size"), Some(&PdfObject::Integer(5)));
+    assert_eq!(document.trailer().get("Prev"), Some(&PdfObject::Integer(three as i64)));
+    assert_eq!(*document.get(ObjectId::new(2, 0)).unwrap(), PdfObject::Integer(42));
+    assert!(document.get(ObjectId::new(1, 0)).unwrap().as_dict().unwrap().has_type("Catalog"));
+    std::fs::remove_file(path).unwrap();
+}
\ No newline at end of file
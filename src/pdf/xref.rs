@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+
+use crate::example::random_access_source::RandomAccessSource;
+use crate::pdf::error::PdfError;
+use crate::pdf::filter::{read_stream, FilterRegistry};
+use crate::pdf::lexer::{is_whitespace, Token};
+use crate::pdf::object::{Dictionary, PdfObject, Parser};
+
+/// how far from the end `startxref` is searched for, the spec puts `%%EOF` within the last 1024 bytes
+const TAIL_LENGTH: u64 = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrefEntry {
+    Free { next: u32, generation: u16 },
+    InUse { offset: u64, generation: u16 },
+    /// stored as the index-th object of the object stream numbered stream
+    Compressed { stream: u32, index: u32 },
+}
+
+/// The cross-reference sections of a file merged into one map, newer sections hiding older ones.
+#[derive(Debug, Clone)]
+pub struct XrefTable {
+    entries: BTreeMap<u32, XrefEntry>,
+    trailer: Dictionary,
+    startxref: u64,
+}
+
+impl XrefTable {
+    /// read the sections starting at `startxref` and every section reached through `/Prev` and `/XRefStm`
+    pub fn read<S: RandomAccessSource>(parser: &mut Parser<S>) -> Result<XrefTable, PdfError> {
+        XrefTable::read_with(parser, &FilterRegistry::new())
+    }
+
+    /// read with filters decoding the xref streams
+    pub fn read_with<S: RandomAccessSource>(parser: &mut Parser<S>, filters: &FilterRegistry) -> Result<XrefTable, PdfError> {
+        let startxref = find_startxref(parser.source())?;
+        let mut table = XrefTable { entries: BTreeMap::new(), trailer: Dictionary::new(), startxref };
+        let mut visited = HashSet::new();
+        let mut next = Some(startxref);
+
+        while let Some(offset) = next.take() {
+            // a /Prev pointing back into the chain would loop forever
+            if !visited.insert(offset) {
+                break;
+            }
+            let (entries, trailer) = read_section(parser, offset, filters)?;
+            table.merge(entries);
+            if let Some(stream_offset) = trailer.get("XRefStm").and_then(PdfObject::as_i64) {
+                // hybrid files keep the entries only newer readers know about in a stream next to the table
+                if visited.insert(stream_offset as u64) {
+                    let (entries, _) = read_section(parser, stream_offset as u64, filters)?;
+                    table.merge(entries);
+                }
+            }
+            next = trailer.get("Prev").and_then(PdfObject::as_i64).map(|prev| prev as u64);
+            if table.trailer.is_empty() {
+                table.trailer = trailer;
+            }
+        }
+        Ok(table)
+    }
+
+    fn merge(&mut self, entries: Vec<(u32, XrefEntry)>) {
+        for (number, entry) in entries {
+            self.entries.entry(number).or_insert(entry);
+        }
+    }
+
+    /// the trailer of the newest section
+    pub fn trailer(&self) -> &Dictionary {
+        &self.trailer
+    }
+
+    pub fn startxref(&self) -> u64 {
+        self.startxref
+    }
+
+    pub fn entries(&self) -> &BTreeMap<u32, XrefEntry> {
+        &self.entries
+    }
+
+    pub fn get(&self, number: u32) -> Option<XrefEntry> {
+        self.entries.get(&number).copied()
+    }
+
+    /// offset of an object stored directly in the file
+    pub fn offset(&self, number: u32) -> Option<u64> {
+        match self.get(number) {
+            Some(XrefEntry::InUse { offset, .. }) => Some(offset),
+            _ => None,
+        }
+    }
+}
+
+/// the offset written after the last `startxref` keyword of the file
+pub fn find_startxref<S: RandomAccessSource>(source: &S) -> Result<u64, PdfError> {
+    let length = source.length()?;
+    let start = length.saturating_sub(TAIL_LENGTH);
+    let mut tail = vec![0u8; (length - start) as usize];
+    source.read_fully_at(start, &mut tail)?;
+
+    let keyword = b"startxref";
+    let found = tail.windows(keyword.len()).rposition(|window| window == keyword)
+        .ok_or_else(|| PdfError::syntax(start, "no startxref near the end of the file"))?;
+    let mut index = found + keyword.len();
+    while index < tail.len() && is_whitespace(tail[index]) {
+        index += 1;
+    }
+    let digits = tail[index..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+    std::str::from_utf8(&tail[index..index + digits]).ok()
+        .and_then(|digits| digits.parse().ok())
+        .filter(|offset| *offset < length)
+        .ok_or_else(|| PdfError::syntax(start + index as u64, "startxref is not followed by an offset in the file"))
+}
+
+fn read_section<S: RandomAccessSource>(parser: &mut Parser<S>, offset: u64, filters: &FilterRegistry)
+                                       -> Result<(Vec<(u32, XrefEntry)>, Dictionary), PdfError> {
+    parser.seek(offset)?;
+    match parser.peek_token(0)? {
+        Some(Token::Keyword(word)) if word == b"xref" => read_table(parser),
+        Some(Token::Integer(_)) => read_xref_stream(parser, filters),
+        _ => Err(PdfError::syntax(offset, "expected a cross-reference table or stream")),
+    }
+}
+
+/// a classic `xref` table: subsections of `start count` followed by `offset generation n|f` lines, then the trailer
+fn read_table<S: RandomAccessSource>(parser: &mut Parser<S>) -> Result<(Vec<(u32, XrefEntry)>, Dictionary), PdfError> {
+    parser.expect_keyword("xref")?;
+    let mut entries = Vec::new();
+    loop {
+        let position = parser.offset();
+        match parser.next_token()? {
+            Some((_, Token::Keyword(word))) if word == b"trailer" => return Ok((entries, parser.parse_dictionary()?)),
+            Some((_, Token::Integer(start))) => {
+                let count = next_integer(parser)?;
+                for number in object_numbers(start, count, position)? {
+                    let entry_position = parser.offset();
+                    let field = next_integer(parser)?;
+                    let generation = u16::try_from(next_integer(parser)?)
+                        .map_err(|_| PdfError::syntax(entry_position, "generation out of range"))?;
+                    let entry = match parser.next_token()? {
+                        Some((_, Token::Keyword(word))) if word == b"n" =>
+                            XrefEntry::InUse { offset: field as u64, generation },
+                        Some((_, Token::Keyword(word))) if word == b"f" =>
+                            XrefEntry::Free { next: field as u32, generation },
+                        _ => return Err(PdfError::syntax(entry_position, "expected an n or f entry")),
+                    };
+                    entries.push((number, entry));
+                }
+            }
+            _ => return Err(PdfError::syntax(position, "expected a subsection or trailer")),
+        }
+    }
+}
+
+/// the object numbers of a subsection, which come from the file and so may be negative or overflow
+fn object_numbers(start: i64, count: i64, position: u64) -> Result<Range<u32>, PdfError> {
+    let start = u32::try_from(start).ok();
+    let end = start.zip(u32::try_from(count).ok()).and_then(|(start, count)| start.checked_add(count));
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(start..end),
+        _ => Err(PdfError::syntax(position, "subsection object numbers out of range")),
+    }
+}
+
+fn next_integer<S: RandomAccessSource>(parser: &mut Parser<S>) -> Result<i64, PdfError> {
+    let position = parser.offset();
+    match parser.next_token()? {
+        Some((_, Token::Integer(value))) if value >= 0 => Ok(value),
+        _ => Err(PdfError::syntax(position, "expected a non-negative integer")),
+    }
+}
+
+/// a `/Type /XRef` stream: fixed width big-endian fields as given by `/W`, for the ranges given by `/Index`. its
+/// dictionary doubles as the trailer
+fn read_xref_stream<S: RandomAccessSource>(parser: &mut Parser<S>, filters: &FilterRegistry)
+                                           -> Result<(Vec<(u32, XrefEntry)>, Dictionary), PdfError> {
+    let position = parser.offset();
+    let stream = match parser.parse_indirect_object()? {
+        (_, PdfObject::Stream(stream)) if stream.dict.has_type("XRef") => stream,
+        _ => return Err(PdfError::syntax(position, "expected a cross-reference stream")),
+    };
+    let dict = &stream.dict;
+    let invalid = |message: &str| PdfError::syntax(position, message);
+
+    let widths = dict.get("W").and_then(PdfObject::as_array)
+        .map(|widths| widths.iter().map(|width| width.as_i64().filter(|width| (0..=8).contains(width))).collect::<Option<Vec<_>>>())
+        .and_then(|widths| widths.filter(|widths| widths.len() == 3))
+        .ok_or_else(|| invalid("xref stream needs /W with three widths of at most 8 bytes"))?;
+    let widths: Vec<usize> = widths.into_iter().map(|width| width as usize).collect();
+    let size = dict.get("Size").and_then(PdfObject::as_i64).ok_or_else(|| invalid("xref stream needs /Size"))?;
+    let ranges = match dict.get("Index").and_then(PdfObject::as_array) {
+        Some(index) => index.chunks(2)
+            .map(|pair| match pair {
+                [start, count] => start.as_i64().zip(count.as_i64()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("xref stream /Index needs pairs of integers"))?,
+        None => vec![(0, size)],
+    };
+
+    let length = dict.get("Length").and_then(PdfObject::as_i64).filter(|length| *length >= 0)
+        .ok_or_else(|| invalid("xref stream needs a direct /Length"))?;
+    let data = read_stream(parser.source(), &stream, length as u64, filters)?;
+    let entry_length: usize = widths.iter().sum();
+    let mut rows = data.chunks_exact(entry_length.max(1));
+    let mut entries = Vec::new();
+    for (start, count) in ranges {
+        for number in object_numbers(start, count, position)? {
+            let row = rows.next().ok_or_else(|| invalid("xref stream data is shorter than its /Index"))?;
+            let (kind, rest) = row.split_at(widths[0]);
+            let (second, third) = rest.split_at(widths[1]);
+            // a missing type field means every entry is in use
+            let kind = if widths[0] == 0 { 1 } else { field(kind) };
+            let (second, third) = (field(second), field(third));
+            let entry = match kind {
+                0 => XrefEntry::Free { next: second as u32, generation: third as u16 },
+                1 => XrefEntry::InUse { offset: second, generation: third as u16 },
+                2 => XrefEntry::Compressed { stream: second as u32, index: third as u32 },
+                // unknown types are to be read as references to the null object
+                _ => continue,
+            };
+            entries.push((number, entry));
+        }
+    }
+    Ok((entries, stream.dict))
+}
+
+fn field(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+/// a file with objects numbered from 1 and a classic xref table, `/Root` being object 1
+#[cfg(test)]
+pub(crate) fn build_pdf(objects: &[&str]) -> Vec<u8> {
+    let mut pdf = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).as_bytes());
+    }
+    let startxref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f\r\n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n\r\n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                                  objects.len() + 1, startxref).as_bytes());
+    pdf
+}
+
+/// append an incremental update replacing or adding the numbered objects, with a table chained through `/Prev`
+#[cfg(test)]
+pub(crate) fn append_update(pdf: &mut Vec<u8>, objects: &[(u32, &str)]) {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let prev = find_startxref(&ArrayRandomAccessSource::new(&pdf[..])).unwrap();
+    let mut offsets = Vec::new();
+    for (number, object) in objects {
+        offsets.push((*number, pdf.len()));
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", number, object).as_bytes());
+    }
+    let startxref = pdf.len();
+    let size = objects.iter().map(|(number, _)| number + 1).max().unwrap_or(1);
+    pdf.extend_from_slice(b"xref\n");
+    for (number, offset) in offsets {
+        pdf.extend_from_slice(format!("{} 1\n{:010} 00000 n\r\n", number, offset).as_bytes());
+    }
+    pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R /Prev {} >>\nstartxref\n{}\n%%EOF\n",
+                                  size, prev, startxref).as_bytes());
+}
+
+#[cfg(test)]
+fn read_xref(pdf: &[u8]) -> Result<XrefTable, PdfError> {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    XrefTable::read(&mut Parser::new(ArrayRandomAccessSource::new(pdf)))
+}
+
+/// bytes with the first occurrence of from replaced
+#[cfg(test)]
+pub(crate) fn replace(bytes: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let start = bytes.windows(from.len()).position(|window| window == from).unwrap();
+    [&bytes[..start], to, &bytes[start + from.len()..]].concat()
+}
+
+#[test]
+fn xref_table_test() {
+    use crate::pdf::object::ObjectId;
+
+    let mut pdf = build_pdf(&["<< /Type /Catalog /Pages 2 0 R >>", "<< /Type /Pages /Kids [] /Count 0 >>"]);
+    let table = read_xref(&pdf).unwrap();
+    assert_eq!(table.get(0), Some(XrefEntry::Free { next: 0, generation: 65535 }));
+    assert_eq!(table.offset(1), Some(15));
+    assert_eq!(&pdf[15..22], b"1 0 obj");
+    assert_eq!(table.trailer().get("Root"), Some(&PdfObject::Reference(ObjectId::new(1, 0))));
+    assert_eq!(table.trailer().get("Size"), Some(&PdfObject::Integer(3)));
+
+    // the update hides the old object 2 and adds object 3, object 1 still comes from the first section
+    let old_two = table.offset(2).unwrap();
+    append_update(&mut pdf, &[(2, "<< /Type /Pages /Kids [3 0 R] /Count 1 >>"), (3, "<< /Type /Page >>")]);
+    let updated = read_xref(&pdf).unwrap();
+    assert_eq!(updated.offset(1), Some(15));
+    let new_two = updated.offset(2).unwrap();
+    assert!(new_two > old_two);
+    assert_eq!(&pdf[new_two as usize..new_two as usize + 7], b"2 0 obj");
+    assert!(updated.offset(3).is_some());
+    assert_eq!(updated.trailer().get("Size"), Some(&PdfObject::Integer(4)));
+    assert_eq!(updated.trailer().get("Prev"), Some(&PdfObject::Integer(table.startxref() as i64)));
+}
+
+#[test]
+fn xref_stream_test() {
+    use crate::pdf::filter::deflate;
+
+    let mut base = build_pdf(&["<< /Type /Catalog >>", "(old)"]);
+    let prev = read_xref(&base).unwrap().startxref();
+    let two = base.len();
+    base.extend_from_slice(b"2 0 obj\n(new)\nendobj\n");
+    let three = base.len();
+
+    // /W [1 2 1]: object 2 in use, object 3 (this stream) in use, object 4 compressed in stream 5 at index 1
+    let mut rows = vec![1u8, (two >> 8) as u8, two as u8, 0];
+    rows.extend_from_slice(&[1, (three >> 8) as u8, three as u8, 0]);
+    rows.extend_from_slice(&[2, 0, 5, 1]);
+    // the same rows with the PNG Up predictor and deflated, the way writers usually store them
+    let mut predicted = Vec::new();
+    let mut previous = [0u8; 4];
+    for row in rows.chunks(4) {
+        predicted.push(2);
+        predicted.extend(row.iter().zip(previous).map(|(byte, up)| byte.wrapping_sub(up)));
+        previous.copy_from_slice(row);
+    }
+
+    for (filter, data) in [("", rows.clone()), ("/Filter /FlateDecode /DecodeParms << /Predictor 12 /Columns 4 >> ", deflate(&predicted))] {
+        let mut pdf = base.clone();
+        pdf.extend_from_slice(format!("3 0 obj\n<< /Type /XRef /W [1 2 1] /Index [2 3] /Size 5 /Root 1 0 R /Prev {} \
+{}/Length {} >>\nstream\n", prev, filter, data.len()).as_bytes());
+        pdf.extend_from_slice(&data);
+        pdf.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF", three).as_bytes());
+
+        let table = read_xref(&pdf).unwrap();
+        assert_eq!(table.offset(2), Some(two as u64));
+        assert_eq!(table.offset(3), Some(three as u64));
+        assert_eq!(table.get(4), Some(XrefEntry::Compressed { stream: 5, index: 1 }));
+        assert_eq!(table.offset(1), Some(15));
+        assert!(table.trailer().has_type("XRef"));
+
+        // the filter goes after the start of the stream object, so its offset stays valid
+        let unknown = replace(&pdf, b"/Length", b"/Filter /JBIG2Decode /Length");
+        assert!(matches!(read_xref(&unknown), Err(PdfError::UnsupportedFilter(name)) if name == "JBIG2Decode"));
+    }
+}
+
+#[test]
+fn xref_broken_test() {
+    let pdf = build_pdf(&["<< /Type /Catalog >>"]);
+    let startxref = read_xref(&pdf).unwrap().startxref();
+
+    // a /Prev back to the same section ends the chain instead of looping
+    let looped = replace(&pdf, b"/Size 2", format!("/Size 2 /Prev {}", startxref).as_bytes());
+    assert_eq!(read_xref(&looped).unwrap().entries().len(), 2);
+
+    let missing = &pdf[..pdf.len() - 30];
+    assert!(matches!(read_xref(missing), Err(PdfError::Syntax { .. })));
+    let outside = replace(&pdf, format!("startxref\n{}", startxref).as_bytes(), b"startxref\n99999");
+    assert!(read_xref(&outside).is_err());
+
+    // subsection numbers that are negative or run past the largest object number fail before any entry is read
+    for start in ["-1", "4294967295"] {
+        let bad = replace(&pdf, b"xref\n0 2", format!("xref\n{} 2", start).as_bytes());
+        assert!(matches!(read_xref(&bad), Err(PdfError::Syntax { .. })));
+    }
+}
\ No newline at end of file
@@ -1,6 +1,11 @@
 use std::error::Error;
 use std::fs;
 
+use crate::example::random_access_source::FileRandomAccessSouce;
+use crate::pdf::document::PdfDocument;
+use crate::pdf::error::PdfError;
+use crate::pdf::text::extract_text;
+
 pub struct Config {
     query: String,
     filename: String,
@@ -25,6 +30,19 @@ impl Config {
 }
 
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    if is_pdf(&config.filename) {
+        for (index, page) in pdf_pages(&config.filename)?.iter().enumerate() {
+            match page {
+                Ok(page) => for line in search(page, &config.query) {
+                    println!("page {}: {}", index + 1, line);
+                },
+                // one unreadable page should not hide matches on the others
+                Err(error) => eprintln!("page {}: skipped, {}", index + 1, error),
+            }
+        }
+        return Ok(());
+    }
+
     let content = fs::read_to_string(config.filename)?;
     for line in search(&content, &config.query) {
         println!("{}", line);
@@ -32,6 +50,16 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn is_pdf(filename: &str) -> bool {
+    filename.to_ascii_lowercase().ends_with(".pdf")
+}
+
+/// the text of each page of a PDF file, or why that page could not be read
+fn pdf_pages(filename: &str) -> Result<Vec<Result<String, PdfError>>, Box<dyn Error>> {
+    let document = PdfDocument::open(FileRandomAccessSouce::new(filename)?)?;
+    Ok(extract_text(&document)?)
+}
+
 fn search<'a>(contents: &'a str, query: &str) -> Vec<&'a str> {
     contents.lines()
         .filter(|line|{line.contains(query)})
@@ -53,4 +81,30 @@ Pick there.
 
         assert_eq!(vec!["safe, fast, productive."], search(contents, query));
     }
+
+    #[test]
+    fn pdf_pages_result() {
+        use crate::pdf::xref::build_pdf;
+
+        let content = "BT /F1 12 Tf 72 700 Td (Rust:) Tj 0 -14 Td (safe, fast, productive.) Tj ET";
+        let pdf = build_pdf(&[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [6 0 R 3 0 R] /Count 2 >>",
+            "<< /Type /Page /Parent 2 0 R /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>",
+            &format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+            "<< /Type /Page /Parent 2 0 R /Contents 7 0 R >>",
+            "<< /Length 4 /Filter /JBIG2Decode >>\nstream\njunk\nendstream",
+        ]);
+        let path = std::env::temp_dir().join(format!("search_text_{}.pdf", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        std::fs::write(&path, pdf).unwrap();
+
+        assert!(is_pdf(&path));
+        // the first page cannot be decoded, the second is still searched
+        let pages = pdf_pages(&path).unwrap();
+        assert!(pages[0].is_err());
+        assert_eq!(vec!["safe, fast, productive."], search(pages[1].as_ref().unwrap(), "duct"));
+        std::fs::remove_file(path).unwrap();
+    }
 }
\ No newline at end of file
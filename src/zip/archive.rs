@@ -0,0 +1,547 @@
+use std::io;
+use std::io::{ErrorKind, Read};
+
+use flate2::bufread::DeflateDecoder;
+use flate2::Crc;
+
+use crate::example::random_access_reader::RandomAccessReader;
+use crate::example::random_access_source::RandomAccessSource;
+use crate::example::window_random_access_source::WindowRandomAccessSource;
+use crate::zip::error::ZipError;
+
+const LOCAL_HEADER: [u8; 4] = *b"PK\x03\x04";
+const CENTRAL_HEADER: [u8; 4] = *b"PK\x01\x02";
+const END_OF_CENTRAL_DIRECTORY: [u8; 4] = *b"PK\x05\x06";
+const ZIP64_END_OF_CENTRAL_DIRECTORY: [u8; 4] = *b"PK\x06\x06";
+const ZIP64_LOCATOR: [u8; 4] = *b"PK\x06\x07";
+
+const LOCAL_HEADER_LENGTH: usize = 30;
+const CENTRAL_HEADER_LENGTH: usize = 46;
+const END_LENGTH: usize = 22;
+const ZIP64_END_LENGTH: usize = 56;
+const ZIP64_LOCATOR_LENGTH: usize = 20;
+/// the end record is followed by a comment of at most this many bytes
+const MAX_COMMENT_LENGTH: usize = 0xFFFF;
+const ZIP64_EXTRA: u16 = 0x0001;
+
+const FLAG_ENCRYPTED: u16 = 0x0001;
+const FLAG_UTF8: u16 = 0x0800;
+
+/// code page 437 0x80..=0xFF, which names without the UTF-8 flag are written in
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{A0}";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionMethod {
+    Stored,
+    Deflated,
+    Other(u16),
+}
+
+impl CompressionMethod {
+    fn from_u16(method: u16) -> CompressionMethod {
+        match method {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflated,
+            other => CompressionMethod::Other(other),
+        }
+    }
+}
+
+/// an entry of the central directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipEntry {
+    pub name: String,
+    pub method: CompressionMethod,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub size: u64,
+    /// position of the local header, past any data prepended to the archive
+    pub header_offset: u64,
+    flags: u16,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+}
+
+/// A ZIP archive read through a `RandomAccessSource`.
+///
+/// Opening reads the end of central directory record, ZIP64 included, and the central directory. Entry data is only
+/// read when asked for, through a window over the source, so nothing is extracted to disk or held in memory.
+pub struct ZipArchive<S: RandomAccessSource> {
+    source: S,
+    entries: Vec<ZipEntry>,
+    comment: Vec<u8>,
+}
+
+impl<S: RandomAccessSource> ZipArchive<S> {
+    pub fn open(source: S) -> Result<ZipArchive<S>, ZipError> {
+        let length = source.length()?;
+        let (end_offset, end) = find_end(&source, length)?;
+        let comment = end[END_LENGTH..].to_vec();
+        if u16_at(&end, 4) != 0 || u16_at(&end, 6) != 0 {
+            return Err(ZipError::Unsupported("archives split over several disks".to_string()));
+        }
+        let mut count = u16_at(&end, 10) as u64;
+        let mut directory_size = u32_at(&end, 12) as u64;
+        let mut directory_offset = u32_at(&end, 16) as u64;
+
+        let zip64 = zip64_end(&source, end_offset)?;
+        // data prepended to the archive, as self-extracting ones have, moves every offset it records
+        let shift = match zip64 {
+            Some((record, shift)) => {
+                count = u64_at(&record, 32);
+                directory_size = u64_at(&record, 40);
+                directory_offset = u64_at(&record, 48);
+                shift
+            }
+            None => directory_offset.checked_add(directory_size)
+                .and_then(|end| end_offset.checked_sub(end))
+                .ok_or_else(|| ZipError::Malformed("central directory ends after its end record".to_string()))?,
+        };
+
+        let start = directory_offset.saturating_add(shift);
+        if directory_size > length.saturating_sub(start) {
+            return Err(ZipError::Malformed("central directory is outside of the archive".to_string()));
+        }
+        let mut directory = vec![0u8; directory_size as usize];
+        source.read_fully_at(start, &mut directory)?;
+
+        // every header takes at least its fixed part, which bounds a broken count
+        let mut entries = Vec::with_capacity(count.min(directory_size / CENTRAL_HEADER_LENGTH as u64) as usize);
+        let mut position = 0;
+        for _ in 0..count {
+            let (mut entry, next) = parse_central_header(&directory, position)?;
+            entry.header_offset = entry.header_offset.checked_add(shift)
+                .ok_or_else(|| ZipError::Malformed(format!("local header of {} is past the largest offset", entry.name)))?;
+            entries.push(entry);
+            position = next;
+        }
+        Ok(ZipArchive { source, entries, comment })
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// the data of entry as stored, compressed or not, as a source of its own
+    pub fn raw_data(&self, entry: &ZipEntry) -> Result<WindowRandomAccessSource<&S>, ZipError> {
+        let mut header = [0u8; LOCAL_HEADER_LENGTH];
+        self.source.read_fully_at(entry.header_offset, &mut header)?;
+        if header[..4] != LOCAL_HEADER {
+            return Err(ZipError::Malformed(format!("no local header for {} at {}", entry.name, entry.header_offset)));
+        }
+        // the local extra field may differ from the central one, only its length matters here
+        let data_offset = entry.header_offset + LOCAL_HEADER_LENGTH as u64
+            + u16_at(&header, 26) as u64 + u16_at(&header, 28) as u64;
+        WindowRandomAccessSource::new(&self.source, data_offset, entry.compressed_size).map_err(|_| {
+            ZipError::Malformed(format!("data of {} is outside of the archive", entry.name))
+        })
+    }
+
+    /// a reader decompressing entry, which fails at the end unless the size and CRC-32 match the central directory
+    pub fn reader(&self, entry: &ZipEntry) -> Result<ZipEntryReader<'_, S>, ZipError> {
+        if entry.is_encrypted() {
+            return Err(ZipError::Unsupported(format!("{} is encrypted", entry.name)));
+        }
+        let data = RandomAccessReader::new(self.raw_data(entry)?);
+        let decoder = match entry.method {
+            CompressionMethod::Stored => Decoder::Stored(data),
+            CompressionMethod::Deflated => Decoder::Deflated(Box::new(DeflateDecoder::new(data))),
+            CompressionMethod::Other(method) =>
+                return Err(ZipError::Unsupported(format!("compression method {} of {}", method, entry.name))),
+        };
+        Ok(ZipEntryReader { decoder, entry: entry.clone(), crc: Crc::new(), readed: 0 })
+    }
+
+    /// the whole decompressed data of entry
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, ZipError> {
+        // the size is only a hint, it may be wrong in a broken archive
+        let mut data = Vec::with_capacity(entry.size.min(1 << 24) as usize);
+        self.reader(entry)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+enum Decoder<'a, S: RandomAccessSource> {
+    Stored(RandomAccessReader<WindowRandomAccessSource<&'a S>>),
+    Deflated(Box<DeflateDecoder<RandomAccessReader<WindowRandomAccessSource<&'a S>>>>),
+}
+
+/// The decompressed data of one entry. Reading past the end checks the size and CRC-32, a mismatch is an
+/// `io::Error` of kind `InvalidData` wrapping a `ZipError`.
+pub struct ZipEntryReader<'a, S: RandomAccessSource> {
+    decoder: Decoder<'a, S>,
+    entry: ZipEntry,
+    crc: Crc,
+    readed: u64,
+}
+
+impl<S: RandomAccessSource> ZipEntryReader<'_, S> {
+    fn check(&self) -> io::Result<()> {
+        if self.readed != self.entry.size {
+            let message = format!("{} has {} bytes or more, expected {}", self.entry.name, self.readed, self.entry.size);
+            return Err(io::Error::new(ErrorKind::InvalidData, ZipError::Malformed(message)));
+        }
+        if self.crc.sum() != self.entry.crc32 {
+            return Err(io::Error::new(ErrorKind::InvalidData, ZipError::Checksum {
+                name: self.entry.name.clone(),
+                expected: self.entry.crc32,
+                actual: self.crc.sum(),
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl<S: RandomAccessSource> Read for ZipEntryReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let readed = match &mut self.decoder {
+            Decoder::Stored(reader) => reader.read(buf)?,
+            Decoder::Deflated(decoder) => decoder.read(buf)?,
+        };
+        self.crc.update(&buf[..readed]);
+        self.readed += readed as u64;
+        // stop a broken entry as soon as it outgrows its size instead of at the end
+        if self.readed > self.entry.size || (readed == 0 && !buf.is_empty()) {
+            self.check()?;
+        }
+        Ok(readed)
+    }
+}
+
+/// the position and bytes, comment included, of the end of central directory record
+fn find_end<S: RandomAccessSource>(source: &S, length: u64) -> Result<(u64, Vec<u8>), ZipError> {
+    let tail_length = length.min((END_LENGTH + MAX_COMMENT_LENGTH) as u64);
+    let mut tail = vec![0u8; tail_length as usize];
+    source.read_fully_at(length - tail_length, &mut tail)?;
+    // search backwards, a comment may itself hold the signature. a record whose comment reaches exactly to the end
+    // is the real one, one whose comment stops short is only taken when there is no such record
+    let candidates: Vec<(usize, usize)> = (0..tail.len().saturating_sub(END_LENGTH - 1)).rev()
+        .filter(|start| tail[*start..*start + 4] == END_OF_CENTRAL_DIRECTORY)
+        .map(|start| (start, start + END_LENGTH + u16_at(&tail, start + 20) as usize))
+        .filter(|(_, end)| *end <= tail.len())
+        .collect();
+    let (start, end) = candidates.iter().find(|(_, end)| *end == tail.len()).or(candidates.first())
+        .ok_or_else(|| ZipError::Malformed("no end of central directory record".to_string()))?;
+    Ok((length - tail_length + *start as u64, tail[*start..*end].to_vec()))
+}
+
+/// the ZIP64 end of central directory record, when a locator precedes the end record at end_offset, with how far
+/// it is past the offset the locator records
+fn zip64_end<S: RandomAccessSource>(source: &S, end_offset: u64) -> Result<Option<(Vec<u8>, u64)>, ZipError> {
+    if end_offset < ZIP64_LOCATOR_LENGTH as u64 {
+        return Ok(None);
+    }
+    let mut locator = [0u8; ZIP64_LOCATOR_LENGTH];
+    source.read_fully_at(end_offset - ZIP64_LOCATOR_LENGTH as u64, &mut locator)?;
+    if locator[..4] != ZIP64_LOCATOR {
+        return Ok(None);
+    }
+    let recorded = u64_at(&locator, 8);
+    let locator_offset = end_offset - ZIP64_LOCATOR_LENGTH as u64;
+    // the record nearly always sits right before the locator, where it is found even when prepended data makes the
+    // recorded offset wrong
+    let mut record = vec![0u8; ZIP64_END_LENGTH];
+    for position in [locator_offset.checked_sub(ZIP64_END_LENGTH as u64), Some(recorded)].into_iter().flatten() {
+        if position < recorded || position > locator_offset.saturating_sub(ZIP64_END_LENGTH as u64) {
+            continue;
+        }
+        source.read_fully_at(position, &mut record)?;
+        if record[..4] == ZIP64_END_OF_CENTRAL_DIRECTORY {
+            return Ok(Some((record, position - recorded)));
+        }
+    }
+    Err(ZipError::Malformed(format!("no ZIP64 end record at {}", recorded)))
+}
+
+/// the entry whose central header starts at position of directory, with the position of the next header
+fn parse_central_header(directory: &[u8], position: usize) -> Result<(ZipEntry, usize), ZipError> {
+    let header = directory.get(position..position + CENTRAL_HEADER_LENGTH)
+        .filter(|header| header[..4] == CENTRAL_HEADER)
+        .ok_or_else(|| ZipError::Malformed(format!("no central header at {} of the directory", position)))?;
+    let name_length = u16_at(header, 28) as usize;
+    let extra_length = u16_at(header, 30) as usize;
+    let comment_length = u16_at(header, 32) as usize;
+    let name_start = position + CENTRAL_HEADER_LENGTH;
+    let next = name_start + name_length + extra_length + comment_length;
+    if next > directory.len() {
+        return Err(ZipError::Malformed(format!("central header at {} runs past the directory", position)));
+    }
+
+    let flags = u16_at(header, 8);
+    let name = &directory[name_start..name_start + name_length];
+    let name = match flags & FLAG_UTF8 {
+        0 => name.iter().map(|byte| cp437(*byte)).collect(),
+        _ => String::from_utf8_lossy(name).into_owned(),
+    };
+    let mut entry = ZipEntry {
+        name,
+        method: CompressionMethod::from_u16(u16_at(header, 10)),
+        crc32: u32_at(header, 16),
+        compressed_size: u32_at(header, 20) as u64,
+        size: u32_at(header, 24) as u64,
+        header_offset: u32_at(header, 42) as u64,
+        flags,
+    };
+
+    // a field set to all ones has its real value in the ZIP64 extra field, the present ones in this order
+    let mut extra = &directory[name_start + name_length..name_start + name_length + extra_length];
+    while extra.len() >= 4 {
+        let (id, length) = (u16_at(extra, 0), u16_at(extra, 2) as usize);
+        let data = extra.get(4..4 + length)
+            .ok_or_else(|| ZipError::Malformed(format!("extra field of {} runs past its header", entry.name)))?;
+        if id == ZIP64_EXTRA {
+            let mut values = data.chunks_exact(8).map(|value| u64_at(value, 0));
+            for field in [&mut entry.size, &mut entry.compressed_size, &mut entry.header_offset] {
+                if *field == u32::MAX as u64 {
+                    *field = values.next().ok_or_else(|| {
+                        ZipError::Malformed(format!("ZIP64 extra field of {} is too short", entry.name))
+                    })?;
+                }
+            }
+        }
+        extra = &extra[4 + length..];
+    }
+    Ok((entry, next))
+}
+
+fn cp437(byte: u8) -> char {
+    match byte {
+        0..=0x7F => byte as char,
+        _ => CP437_HIGH.chars().nth(byte as usize - 0x80).unwrap_or(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+fn u16_at(bytes: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([bytes[position], bytes[position + 1]])
+}
+
+fn u32_at(bytes: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes(bytes[position..position + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], position: usize) -> u64 {
+    u64::from_le_bytes(bytes[position..position + 8].try_into().unwrap())
+}
+
+/// an archive of (name, data, deflate) entries, with every size and offset in ZIP64 fields when zip64 is set
+#[cfg(test)]
+pub(crate) fn build_zip(entries: &[(&str, &[u8], bool)], zip64: bool) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data, deflate) in entries {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let stored = match deflate {
+            true => {
+                let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            false => data.to_vec(),
+        };
+        let method: u16 = if *deflate { 8 } else { 0 };
+        let offset = archive.len() as u64;
+        let small = |value: u64| if zip64 { u32::MAX } else { value as u32 };
+
+        let mut fields = Vec::new();
+        fields.extend_from_slice(&20u16.to_le_bytes());
+        fields.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+        fields.extend_from_slice(&method.to_le_bytes());
+        fields.extend_from_slice(&[0; 4]);
+        fields.extend_from_slice(&crc.sum().to_le_bytes());
+        fields.extend_from_slice(&small(stored.len() as u64).to_le_bytes());
+        fields.extend_from_slice(&small(data.len() as u64).to_le_bytes());
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        let mut extra = Vec::new();
+        if zip64 {
+            extra.extend_from_slice(&ZIP64_EXTRA.to_le_bytes());
+            extra.extend_from_slice(&24u16.to_le_bytes());
+            extra.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            extra.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+            extra.extend_from_slice(&offset.to_le_bytes());
+        }
+        fields.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+
+        archive.extend_from_slice(&LOCAL_HEADER);
+        archive.extend_from_slice(&fields);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&extra);
+        archive.extend_from_slice(&stored);
+
+        directory.extend_from_slice(&CENTRAL_HEADER);
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&small(offset).to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+        directory.extend_from_slice(&extra);
+    }
+
+    let directory_offset = archive.len() as u64;
+    archive.extend_from_slice(&directory);
+    let count = entries.len() as u64;
+    if zip64 {
+        let record_offset = archive.len() as u64;
+        archive.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIRECTORY);
+        archive.extend_from_slice(&44u64.to_le_bytes());
+        archive.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        archive.extend_from_slice(&count.to_le_bytes());
+        archive.extend_from_slice(&count.to_le_bytes());
+        archive.extend_from_slice(&(directory.len() as u64).to_le_bytes());
+        archive.extend_from_slice(&directory_offset.to_le_bytes());
+        archive.extend_from_slice(&ZIP64_LOCATOR);
+        archive.extend_from_slice(&0u32.to_le_bytes());
+        archive.extend_from_slice(&record_offset.to_le_bytes());
+        archive.extend_from_slice(&1u32.to_le_bytes());
+    }
+    archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY);
+    archive.extend_from_slice(&[0; 4]);
+    let (count, size, offset) = match zip64 {
+        true => (u16::MAX, u32::MAX, u32::MAX),
+        false => (count as u16, directory.len() as u32, directory_offset as u32),
+    };
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&size.to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive
+}
+
+#[test]
+fn zip_archive_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    assert_eq!(CP437_HIGH.chars().count(), 128);
+    let text = "safe, fast, productive.\n".repeat(200);
+    let bytes = build_zip(&[("docs/", b"", false), ("docs/readme.txt", text.as_bytes(), true),
+                            ("stored.bin", &[0, 1, 2, 3, 4, 5, 6, 7], false)], false);
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(bytes.clone())).unwrap();
+    let names: Vec<&str> = archive.entries().iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, vec!["docs/", "docs/readme.txt", "stored.bin"]);
+    assert!(archive.entries()[0].is_dir());
+    assert_eq!(archive.read(&archive.entries()[0]).unwrap(), b"");
+
+    let readme = archive.by_name("docs/readme.txt").unwrap();
+    assert_eq!(readme.method, CompressionMethod::Deflated);
+    assert!(readme.compressed_size < readme.size);
+    assert_eq!(archive.read(readme).unwrap(), text.as_bytes());
+    // small reads go through the same checks
+    let mut reader = archive.reader(readme).unwrap();
+    let mut chunk = [0u8; 7];
+    let mut read_back = Vec::new();
+    loop {
+        match reader.read(&mut chunk).unwrap() {
+            0 => break,
+            count => read_back.extend_from_slice(&chunk[..count]),
+        }
+    }
+    assert_eq!(read_back, text.as_bytes());
+
+    // a stored entry is a window over the archive
+    let stored = archive.by_name("stored.bin").unwrap();
+    let window = archive.raw_data(stored).unwrap();
+    assert_eq!(window.length().unwrap(), 8);
+    assert_eq!(window.get(5).unwrap(), Some(5));
+
+    // prepended data and a comment
+    let mut shifted = b"#!/bin/sh\nexit 0\n".to_vec();
+    shifted.extend_from_slice(&bytes);
+    let comment_length = shifted.len() - 2;
+    shifted[comment_length..].copy_from_slice(&9u16.to_le_bytes());
+    shifted.extend_from_slice(b"a comment");
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(shifted)).unwrap();
+    assert_eq!(archive.comment(), b"a comment");
+    assert_eq!(archive.read(archive.by_name("stored.bin").unwrap()).unwrap(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+    // a comment holding what looks like an end record does not hide the real one
+    let mut fake = bytes.clone();
+    let comment_length = fake.len() - 2;
+    fake[comment_length..].copy_from_slice(&(END_LENGTH as u16 + 2).to_le_bytes());
+    fake.extend_from_slice(&END_OF_CENTRAL_DIRECTORY);
+    fake.extend_from_slice(&[0; END_LENGTH - 4]);
+    fake.extend_from_slice(b"..");
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(fake)).unwrap();
+    assert_eq!(archive.comment().len(), END_LENGTH + 2);
+    assert_eq!(archive.read(archive.by_name("stored.bin").unwrap()).unwrap(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+
+    assert!(matches!(ZipArchive::open(ArrayRandomAccessSource::new(vec![0u8; 100])), Err(ZipError::Malformed(_))));
+}
+
+#[test]
+fn zip64_archive_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let bytes = build_zip(&[("a.txt", b"first entry", false), ("b.txt", &[b'b'; 5000], true)], true);
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(bytes)).unwrap();
+    assert_eq!(archive.entries().len(), 2);
+    let second = archive.by_name("b.txt").unwrap();
+    assert_eq!(second.size, 5000);
+    assert!(second.header_offset > 0 && second.header_offset < u32::MAX as u64);
+    assert_eq!(archive.read(archive.by_name("a.txt").unwrap()).unwrap(), b"first entry");
+    assert_eq!(archive.read(second).unwrap(), vec![b'b'; 5000]);
+
+    // prepended data moves the ZIP64 records too
+    let mut shifted = b"#!/bin/sh\nexit 0\n".to_vec();
+    shifted.extend_from_slice(&build_zip(&[("a.txt", b"first entry", false)], true));
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(shifted.clone())).unwrap();
+    assert_eq!(archive.read(archive.by_name("a.txt").unwrap()).unwrap(), b"first entry");
+
+    // and a ZIP64 header offset that the shift would carry past the largest offset is malformed, not an overflow
+    let central = shifted.windows(4).position(|window| window == CENTRAL_HEADER).unwrap();
+    let offset = central + CENTRAL_HEADER_LENGTH + "a.txt".len() + 4 + 16;
+    shifted[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(ZipArchive::open(ArrayRandomAccessSource::new(shifted)), Err(ZipError::Malformed(_))));
+}
+
+#[test]
+fn zip_errors_test() {
+    use crate::example::array_random_access_source::ArrayRandomAccessSource;
+
+    let mut bytes = build_zip(&[("plain.txt", b"plain data", false), ("packed.txt", &[b'p'; 300], true)], false);
+    let data = bytes.windows(10).position(|window| window == b"plain data").unwrap();
+    bytes[data] = b'P';
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(bytes.clone())).unwrap();
+    match archive.read(&archive.entries()[0]) {
+        Err(ZipError::Checksum { name, .. }) => assert_eq!(name, "plain.txt"),
+        other => panic!("unexpected {:?}", other),
+    }
+    let mut entry = archive.entries()[1].clone();
+    entry.size -= 1;
+    assert!(matches!(archive.read(&entry), Err(ZipError::Malformed(_))));
+    entry.flags |= FLAG_ENCRYPTED;
+    assert!(matches!(archive.reader(&entry), Err(ZipError::Unsupported(_))));
+    entry.flags = 0;
+    entry.method = CompressionMethod::Other(12);
+    assert!(matches!(archive.reader(&entry), Err(ZipError::Unsupported(_))));
+
+    // a name without the UTF-8 flag is code page 437
+    let mut bytes = build_zip(&[("cafX", b"", false)], false);
+    for index in 0..bytes.len() - 3 {
+        match &bytes[index..index + 4] {
+            b"cafX" => bytes[index + 3] = 0x82,
+            [20, 0, 0, 8] => bytes[index + 3] = 0,
+            _ => {}
+        }
+    }
+    let archive = ZipArchive::open(ArrayRandomAccessSource::new(bytes)).unwrap();
+    assert_eq!(archive.entries()[0].name, "café");
+}
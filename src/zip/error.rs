@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum ZipError {
+    Io(io::Error),
+    /// the records of the archive are broken
+    Malformed(String),
+    /// a valid feature this reader does not implement, such as encryption or a compression method
+    Unsupported(String),
+    /// the data of the named entry does not match the CRC-32 of the central directory
+    Checksum { name: String, expected: u32, actual: u32 },
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZipError::Io(error) => write!(f, "read error: {}", error),
+            ZipError::Malformed(message) => write!(f, "malformed archive: {}", message),
+            ZipError::Unsupported(message) => write!(f, "unsupported: {}", message),
+            ZipError::Checksum { name, expected, actual } =>
+                write!(f, "CRC-32 of {} is {:08x}, expected {:08x}", name, actual, expected),
+        }
+    }
+}
+
+impl Error for ZipError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ZipError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ZipError {
+    fn from(error: io::Error) -> ZipError {
+        // the entry readers report checksum failures through io::Error, unwrap them again
+        match error.get_ref().map(|inner| inner.is::<ZipError>()) {
+            Some(true) => *error.into_inner().unwrap().downcast::<ZipError>().unwrap(),
+            _ => ZipError::Io(error),
+        }
+    }
+}